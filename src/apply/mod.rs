@@ -0,0 +1,341 @@
+use anyhow::Context;
+use gix::bstr::BStr;
+use gix::filter::plumbing::pipeline::convert::ToGitOutcome;
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::git::open_repository;
+use crate::journal::{self, Move, MovedFile, Run};
+use crate::process::{Plan, Rename};
+
+#[cfg(test)]
+mod tests;
+
+/// Moves every rename inside the checked out worktree and stages
+/// the rename in the git index. Directories are moved with all their files.
+///
+/// Nothing is touched unless all sources exist and none of the targets do, and files
+/// already moved are moved back if a later one fails. The run is recorded in a journal
+/// under `.git/fmr/`, see [`undo`].
+pub fn apply(repo_path: &Path, renames: &[Rename]) -> anyhow::Result<Vec<Rename>> {
+    let repo = open_repository(repo_path)?;
+    let work_dir = repo
+        .work_dir()
+        .with_context(|| format!("Repository has no worktree: {:?}", repo_path))?
+        .to_path_buf();
+
+    let mut index = repo.open_index()?;
+
+    // Validate the whole plan before moving anything
+    let mut targets = HashSet::new();
+    for Rename { source, target, .. } in renames {
+        if !targets.insert(target) {
+            anyhow::bail!("Several scripts would be renamed to {:?}", target);
+        }
+        if !work_dir.join(source).exists() {
+            anyhow::bail!("Source file is not checked out: {:?}", source);
+        }
+        if tracked_entries(&index, source).is_empty() {
+            anyhow::bail!("Source file is not tracked: {:?}", source);
+        }
+        if work_dir.join(target).exists() || index.entry_by_path(BStr::new(target)).is_some() {
+            return Err(Error::TargetCollision {
+                path: target.clone(),
+            }
+            .into());
+        }
+    }
+
+    let pairs: Vec<(&str, &str)> = renames
+        .iter()
+        .map(|rename| (rename.source.as_str(), rename.target.as_str()))
+        .collect();
+    let files = move_all(&work_dir, &mut index, &pairs)?;
+
+    let moves: Vec<Move> = renames
+        .iter()
+        .zip(files)
+        .map(|(rename, files)| Move {
+            source: rename.source.clone(),
+            target: rename.target.clone(),
+            files,
+        })
+        .collect();
+
+    if !moves.is_empty() {
+        let run = journal::record(repo.git_dir(), moves).inspect_err(|_| {
+            roll_back(&work_dir, &pairs);
+        })?;
+
+        // The cached tree no longer matches the entries
+        if let Err(err) = index.write(without_tree_cache()) {
+            roll_back(&work_dir, &pairs);
+            let _ = journal::remove(repo.git_dir(), run.id);
+            return Err(err.into());
+        }
+    }
+
+    Ok(renames.to_vec())
+}
+
+/// Fails unless HEAD is the source commit of the saved `plan` and every source is
+/// staged with the blob, or for directories the tree, it was planned with. This way
+/// applying a reviewed plan moves exactly the reviewed scripts.
+pub fn verify_checkout(repo_path: &Path, plan: &Plan) -> anyhow::Result<()> {
+    let repo = open_repository(repo_path)?;
+
+    let head = repo.head_commit()?.id.to_string();
+    if head != plan.source_commit {
+        return Err(Error::StalePlan {
+            branch: "HEAD".to_string(),
+            planned: plan.source_commit.clone(),
+            current: head,
+        }
+        .into());
+    }
+
+    let index = repo.open_index()?;
+    for rename in &plan.renames {
+        let planned = gix::ObjectId::from_hex(rename.blob.as_bytes())
+            .with_context(|| format!("Invalid blob id in the plan: {}", rename.blob))?;
+
+        let staged: Vec<(String, gix::ObjectId)> = tracked_entries(&index, &rename.source)
+            .into_iter()
+            .map(|(path, id, _, _)| (path, id))
+            .collect();
+
+        let object = repo.find_object(planned)?;
+        let expected = if object.kind == gix::object::Kind::Tree {
+            let mut recorder = gix::traverse::tree::Recorder::default();
+            object.into_tree().traverse().breadthfirst(&mut recorder)?;
+
+            let mut files: Vec<(String, gix::ObjectId)> = recorder
+                .records
+                .into_iter()
+                .filter(|entry| !entry.mode.is_tree())
+                .map(|entry| (format!("{}/{}", rename.source, entry.filepath), entry.oid))
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![(rename.source.clone(), planned)]
+        };
+
+        if staged != expected {
+            return Err(Error::ChangedSincePlan {
+                path: rename.source.clone(),
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves the renames of the journaled run `id`, or of the latest run, back to their
+/// original paths and drops its journal.
+///
+/// Nothing is touched unless every moved file still has the staged and checked out
+/// content it was renamed with and none of the original paths is taken. Checked out
+/// files are compared after `core.autocrlf` and clean filters, like `git status` does.
+pub fn undo(repo_path: &Path, id: Option<u32>) -> anyhow::Result<Run> {
+    let repo = open_repository(repo_path)?;
+    let work_dir = repo
+        .work_dir()
+        .with_context(|| format!("Repository has no worktree: {:?}", repo_path))?
+        .to_path_buf();
+    let git_dir = repo.git_dir().to_path_buf();
+
+    let run = journal::load(&git_dir, id)?;
+    let mut index = repo.open_index()?;
+    let (mut pipeline, _) = repo.filter_pipeline(None)?;
+
+    // Validate the whole run before moving anything back
+    for Move { source, files, .. } in &run.moves {
+        if work_dir.join(source).exists() || !tracked_entries(&index, source).is_empty() {
+            return Err(Error::TargetCollision {
+                path: source.clone(),
+            }
+            .into());
+        }
+
+        for file in files {
+            let blob = gix::ObjectId::from_hex(file.blob.as_bytes())
+                .with_context(|| format!("Invalid blob id in the journal: {}", file.blob))?;
+            let content = repo.find_object(blob)?.detach().data;
+
+            let staged = index
+                .entry_by_path(BStr::new(&file.target))
+                .map(|entry| entry.id);
+            let checked_out = cleaned_content(&mut pipeline, &index, &work_dir, &file.target)?;
+
+            if staged != Some(blob) || checked_out.as_ref() != Some(&content) {
+                return Err(Error::ChangedSinceApply {
+                    path: file.target.clone(),
+                }
+                .into());
+            }
+        }
+    }
+
+    let pairs: Vec<(&str, &str)> = run
+        .moves
+        .iter()
+        .rev()
+        .map(|moved| (moved.target.as_str(), moved.source.as_str()))
+        .collect();
+    move_all(&work_dir, &mut index, &pairs)?;
+
+    if let Err(err) = index.write(without_tree_cache()) {
+        roll_back(&work_dir, &pairs);
+        return Err(err.into());
+    }
+
+    journal::remove(&git_dir, run.id)?;
+
+    Ok(run)
+}
+
+/// Reads the checked out file `path` as git would store it, after line ending conversion
+/// and clean filters, `None` if it is missing.
+fn cleaned_content(
+    pipeline: &mut gix::filter::Pipeline<'_>,
+    index: &gix::index::File,
+    work_dir: &Path,
+    path: &str,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let Ok(file) = std::fs::File::open(work_dir.join(path)) else {
+        return Ok(None);
+    };
+
+    let mut content = Vec::new();
+    match pipeline.convert_to_git(file, Path::new(path), index)? {
+        ToGitOutcome::Unchanged(mut file) => {
+            file.read_to_end(&mut content)?;
+        }
+        ToGitOutcome::Process(mut stream) => {
+            stream.read_to_end(&mut content)?;
+        }
+        ToGitOutcome::Buffer(buffer) => content.extend_from_slice(buffer),
+    }
+
+    Ok(Some(content))
+}
+
+/// Moves every `(source, target)` pair with [`move_tracked`] and returns the moved files
+/// of each. If one fails, the pairs moved so far are moved back in the worktree, the
+/// index is left to the caller to discard.
+fn move_all(
+    work_dir: &Path,
+    index: &mut gix::index::File,
+    pairs: &[(&str, &str)],
+) -> anyhow::Result<Vec<Vec<MovedFile>>> {
+    let mut files = Vec::with_capacity(pairs.len());
+
+    for (position, (source, target)) in pairs.iter().enumerate() {
+        match move_tracked(work_dir, index, source, target) {
+            Ok(moved) => files.push(moved),
+            Err(err) => {
+                // Includes the failed pair, its files may have been moved already
+                roll_back(work_dir, &pairs[..=position]);
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Moves the `(source, target)` pairs back in the worktree, last one first. Pairs which
+/// weren't moved are skipped, failures are ignored as there's already an error to report.
+fn roll_back(work_dir: &Path, pairs: &[(&str, &str)]) {
+    for (source, target) in pairs.iter().rev() {
+        let source_path = work_dir.join(source);
+        let target_path = work_dir.join(target);
+
+        if target_path.exists() && !source_path.exists() {
+            let _ = std::fs::rename(&target_path, &source_path);
+        }
+    }
+}
+
+/// Moves the file or directory `source` to `target` in the worktree and re-stages
+/// the same blobs under the new path.
+fn move_tracked(
+    work_dir: &Path,
+    index: &mut gix::index::File,
+    source: &str,
+    target: &str,
+) -> anyhow::Result<Vec<MovedFile>> {
+    let source_path = work_dir.join(source);
+    let target_path = work_dir.join(target);
+
+    if let Some(parent) = target_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Can't create directory: {:?}", parent))?;
+    }
+    std::fs::rename(&source_path, &target_path)
+        .with_context(|| format!("Can't move {:?} to {:?}", source, target))?;
+
+    let entries = tracked_entries(index, source);
+
+    index.remove_entries(|_, path, _| is_within(path, source));
+
+    let mut files = Vec::new();
+    for (path, id, flags, mode) in entries {
+        let moved_path = format!("{}{}", target, &path[source.len()..]);
+
+        let metadata = gix::index::fs::Metadata::from_path_no_follow(&work_dir.join(&moved_path))?;
+        let stat = gix::index::entry::Stat::from_fs(&metadata)?;
+        index.dangerously_push_entry(stat, id, flags, mode, BStr::new(&moved_path));
+
+        files.push(MovedFile {
+            source: path,
+            target: moved_path,
+            blob: id.to_string(),
+        });
+    }
+    index.sort_entries();
+
+    Ok(files)
+}
+
+/// Index entries of the file `source` or of the files inside the directory `source`.
+fn tracked_entries(
+    index: &gix::index::File,
+    source: &str,
+) -> Vec<(
+    String,
+    gix::ObjectId,
+    gix::index::entry::Flags,
+    gix::index::entry::Mode,
+)> {
+    index
+        .entries()
+        .iter()
+        .map(|entry| (entry, entry.path(index)))
+        .filter(|(_, path)| is_within(path, source))
+        .map(|(entry, path)| (path.to_string(), entry.id, entry.flags, entry.mode))
+        .collect()
+}
+
+fn is_within(path: &BStr, source: &str) -> bool {
+    path == BStr::new(source)
+        || path
+            .strip_prefix(source.as_bytes())
+            .is_some_and(|rest| rest.starts_with(b"/"))
+}
+
+/// Write options leaving out the cached trees, they no longer match the moved entries.
+fn without_tree_cache() -> gix::index::write::Options {
+    gix::index::write::Options {
+        extensions: gix::index::write::Extensions::Given {
+            tree_cache: false,
+            end_of_index_entry: true,
+        },
+        ..Default::default()
+    }
+}
@@ -0,0 +1,458 @@
+use std::path::{Path, PathBuf};
+
+use crate::git::tests::internal::{commit_files, create_test_repository};
+
+use crate::error::find_error;
+use crate::naming::ScriptKind;
+use crate::process::{process, Configuration, Layout, Plan, Rename};
+
+use super::{apply, undo, verify_checkout};
+
+fn rename(source: &str, target: &str) -> Rename {
+    Rename {
+        source: source.to_string(),
+        target: target.to_string(),
+        blob: String::new(),
+        kind: ScriptKind::Versioned,
+        code: None,
+    }
+}
+
+#[test]
+fn apply_moves_and_stages() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let renames = vec![rename(
+        "db/migrate/new/01__file_of_my_life.txt",
+        "db/migrate/1/V20240102.01__file_of_my_life.txt",
+    )];
+
+    let moved = apply(temp_dir.path(), &renames).unwrap();
+
+    assert_eq!(moved, renames);
+    assert!(!temp_dir
+        .path()
+        .join("db/migrate/new/01__file_of_my_life.txt")
+        .exists());
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/1/V20240102.01__file_of_my_life.txt")
+        .is_file());
+
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    assert!(index
+        .get_path(Path::new("db/migrate/new/01__file_of_my_life.txt"), 0)
+        .is_none());
+    assert!(index
+        .get_path(
+            Path::new("db/migrate/1/V20240102.01__file_of_my_life.txt"),
+            0
+        )
+        .is_some());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn apply_refuses_existing_target() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let renames = vec![rename(
+        "db/migrate/new/01__file_of_my_life.txt",
+        "db/migrate/1/V20240101.01__file1.txt",
+    )];
+
+    let result = apply(temp_dir.path(), &renames);
+
+    assert!(result.is_err());
+    let err = result.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Target file already exists: \"db/migrate/1/V20240101.01__file1.txt\""
+    );
+    assert_eq!(find_error(&err).unwrap().exit_code(), 15);
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/new/01__file_of_my_life.txt")
+        .is_file());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn apply_moves_directories() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/V20240101.01__init/up.txt".to_string()],
+        vec![
+            "db/migrate/new/01__users/up.txt".to_string(),
+            "db/migrate/new/01__users/down.txt".to_string(),
+            "db/migrate/new/01__users_extra.txt".to_string(),
+        ],
+    );
+
+    let renames = vec![rename(
+        "db/migrate/new/01__users",
+        "db/migrate/V20240101.02__users",
+    )];
+
+    apply(temp_dir.path(), &renames).unwrap();
+
+    assert!(!temp_dir.path().join("db/migrate/new/01__users").exists());
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/V20240101.02__users/down.txt")
+        .is_file());
+
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    for file in ["up.txt", "down.txt"] {
+        assert!(index
+            .get_path(
+                Path::new("db/migrate/new/01__users").join(file).as_path(),
+                0
+            )
+            .is_none());
+        assert!(index
+            .get_path(
+                Path::new("db/migrate/V20240101.02__users")
+                    .join(file)
+                    .as_path(),
+                0
+            )
+            .is_some());
+    }
+    assert!(index
+        .get_path(Path::new("db/migrate/new/01__users_extra.txt"), 0)
+        .is_some());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn undo_moves_back_the_latest_run() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/V20240101.01__init/up.txt".to_string()],
+        vec![
+            "db/migrate/new/01__users/up.txt".to_string(),
+            "db/migrate/new/02__roles.txt".to_string(),
+        ],
+    );
+
+    apply(
+        temp_dir.path(),
+        &[rename(
+            "db/migrate/new/01__users",
+            "db/migrate/V20240101.02__users",
+        )],
+    )
+    .unwrap();
+    apply(
+        temp_dir.path(),
+        &[rename(
+            "db/migrate/new/02__roles.txt",
+            "db/migrate/V20240101.03__roles.txt",
+        )],
+    )
+    .unwrap();
+
+    let run = undo(temp_dir.path(), None).unwrap();
+
+    assert_eq!(run.id, 2);
+    assert_eq!(
+        run.moves[0].files[0].target,
+        "db/migrate/V20240101.03__roles.txt"
+    );
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/new/02__roles.txt")
+        .is_file());
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/V20240101.02__users/up.txt")
+        .is_file());
+
+    let run = undo(temp_dir.path(), Some(1)).unwrap();
+
+    assert_eq!(
+        run.moves[0].files[0].source,
+        "db/migrate/new/01__users/up.txt"
+    );
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/new/01__users/up.txt")
+        .is_file());
+    assert!(!temp_dir
+        .path()
+        .join("db/migrate/V20240101.02__users")
+        .exists());
+
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    assert!(index
+        .get_path(Path::new("db/migrate/new/01__users/up.txt"), 0)
+        .is_some());
+    assert!(index
+        .get_path(Path::new("db/migrate/V20240101.02__users/up.txt"), 0)
+        .is_none());
+    assert_eq!(
+        undo(temp_dir.path(), None).unwrap_err().to_string(),
+        "No applied run to undo"
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn undo_refuses_changed_files() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    apply(
+        temp_dir.path(),
+        &[rename(
+            "db/migrate/new/01__file_of_my_life.txt",
+            "db/migrate/1/V20240102.01__file_of_my_life.txt",
+        )],
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir
+            .path()
+            .join("db/migrate/1/V20240102.01__file_of_my_life.txt"),
+        "changed",
+    )
+    .unwrap();
+
+    let err = undo(temp_dir.path(), None).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "File changed since it was renamed: \"db/migrate/1/V20240102.01__file_of_my_life.txt\""
+    );
+    assert_eq!(find_error(&err).unwrap().exit_code(), 17);
+    assert!(!temp_dir
+        .path()
+        .join("db/migrate/new/01__file_of_my_life.txt")
+        .exists());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn apply_refuses_duplicate_targets() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![
+            "db/migrate/new/a/R__view.txt".to_string(),
+            "db/migrate/new/b/R__view.txt".to_string(),
+        ],
+    );
+
+    let renames = vec![
+        rename("db/migrate/new/a/R__view.txt", "db/migrate/1/R__view.txt"),
+        rename("db/migrate/new/b/R__view.txt", "db/migrate/1/R__view.txt"),
+    ];
+
+    let err = apply(temp_dir.path(), &renames).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Several scripts would be renamed to \"db/migrate/1/R__view.txt\""
+    );
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/new/a/R__view.txt")
+        .is_file());
+    assert!(!temp_dir.path().join("db/migrate/1/R__view.txt").exists());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn apply_rolls_back_when_a_move_fails() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![
+            "db/migrate/new/01__first.txt".to_string(),
+            "db/migrate/new/02__second.txt".to_string(),
+        ],
+    );
+
+    // The parent of the second target is a file, so its folder can't be created
+    let renames = vec![
+        rename(
+            "db/migrate/new/01__first.txt",
+            "db/migrate/1/V20240102.01__first.txt",
+        ),
+        rename(
+            "db/migrate/new/02__second.txt",
+            "db/migrate/1/V20240101.01__file1.txt/V20240102.02__second.txt",
+        ),
+    ];
+
+    assert!(apply(temp_dir.path(), &renames).is_err());
+
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/new/01__first.txt")
+        .is_file());
+    assert!(!temp_dir
+        .path()
+        .join("db/migrate/1/V20240102.01__first.txt")
+        .exists());
+
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    assert!(index
+        .get_path(Path::new("db/migrate/new/01__first.txt"), 0)
+        .is_some());
+    assert!(index
+        .get_path(Path::new("db/migrate/1/V20240102.01__first.txt"), 0)
+        .is_none());
+    assert!(!repo.path().join("fmr").exists());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn undo_compares_after_line_ending_conversion() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![],
+    );
+    commit_files(
+        &repo,
+        &["db/migrate/new/01__lines.txt"],
+        "first\nsecond\n",
+        1_700_000_000,
+    );
+    repo.config()
+        .unwrap()
+        .set_bool("core.autocrlf", true)
+        .unwrap();
+
+    apply(
+        temp_dir.path(),
+        &[rename(
+            "db/migrate/new/01__lines.txt",
+            "db/migrate/1/V20240102.01__lines.txt",
+        )],
+    )
+    .unwrap();
+    // As a checkout on Windows would have written it
+    std::fs::write(
+        temp_dir.path().join("db/migrate/1/V20240102.01__lines.txt"),
+        "first\r\nsecond\r\n",
+    )
+    .unwrap();
+
+    undo(temp_dir.path(), None).unwrap();
+
+    assert!(temp_dir
+        .path()
+        .join("db/migrate/new/01__lines.txt")
+        .is_file());
+
+    temp_dir.close().unwrap();
+}
+
+fn plan(repo_path: &Path, layout: Layout) -> Plan {
+    let config = Configuration {
+        repo_path: repo_path.to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        layout,
+        ..Default::default()
+    };
+
+    process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap()
+}
+
+fn stage(repo: &git2::Repository, path: &str, content: &str) {
+    std::fs::write(repo.workdir().unwrap().join(path), content).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(path)).unwrap();
+    index.write().unwrap();
+}
+
+#[test]
+fn verify_checkout_refuses_staged_changes() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![
+            "db/migrate/new/01__file_of_my_life.txt".to_string(),
+            "db/migrate/new/02__second.txt".to_string(),
+        ],
+    );
+
+    let plan = plan(temp_dir.path(), Layout::Files);
+    verify_checkout(temp_dir.path(), &plan).unwrap();
+
+    stage(&repo, "db/migrate/new/02__second.txt", "changed");
+
+    let err = verify_checkout(temp_dir.path(), &plan).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Source file changed since the plan was made: \"db/migrate/new/02__second.txt\""
+    );
+    assert_eq!(find_error(&err).unwrap().exit_code(), 18);
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn verify_checkout_compares_directory_trees() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/V20240101.01__init/up.txt".to_string()],
+        vec![
+            "db/migrate/new/01__users/up.txt".to_string(),
+            "db/migrate/new/01__users/down.txt".to_string(),
+        ],
+    );
+
+    let plan = plan(temp_dir.path(), Layout::Directories);
+    assert_eq!(plan.renames.len(), 1);
+    verify_checkout(temp_dir.path(), &plan).unwrap();
+
+    // A file added to the directory after the plan was made
+    stage(&repo, "db/migrate/new/01__users/seed.txt", "seed");
+
+    let err = verify_checkout(temp_dir.path(), &plan).unwrap_err();
+
+    assert_eq!(find_error(&err).unwrap().exit_code(), 18);
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn verify_checkout_needs_the_source_commit() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let plan = plan(temp_dir.path(), Layout::Files);
+
+    repo.set_head("refs/heads/master").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+
+    let err = verify_checkout(temp_dir.path(), &plan).unwrap_err();
+
+    assert_eq!(find_error(&err).unwrap().exit_code(), 16);
+
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,107 @@
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::git::{find_files_in_branch, find_new_files_in_branch};
+use crate::naming::ScriptKind;
+use crate::process::{
+    find_anchor, migration_directories, parse_scripts, process, Configuration, Edited, Layout,
+    Rename,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Problems a merge of the source branch would run into.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub collisions: Vec<Collision>,
+    /// Unversioned scripts which still need to be renamed
+    pub pending: Vec<Rename>,
+    /// Released target scripts which were changed on the source branch
+    pub edited: Vec<Edited>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.collisions.is_empty() && self.pending.is_empty() && self.edited.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Collision {
+    /// The code is already used by a script in the target branch
+    Duplicate { path: String, existing: String },
+    /// The code is not newer than the last script in the target branch
+    OutOfOrder { path: String, anchor: String },
+}
+
+/// Runs the same analysis as [`process`] and additionally validates the versioned
+/// scripts which were added on the source branch.
+pub fn check(config: Configuration, today: chrono::NaiveDate) -> anyhow::Result<Report> {
+    let mut report = Report::default();
+
+    let target_files = find_files_in_branch(
+        config.repo_path.as_path(),
+        &config.target_branch,
+        config.extension_filter.as_deref(),
+        config.target_directory_filter.as_deref(),
+    )
+    .with_context(|| format!("Can't open target branch: {0}", &config.target_branch))?;
+
+    let source_files: Vec<String> = find_new_files_in_branch(
+        config.repo_path.as_path(),
+        &config.source_branch,
+        &config.target_branch,
+        config.extension_filter.as_deref(),
+        config.target_directory_filter.as_deref(),
+    )
+    .with_context(|| format!("Can't open source branch: {0}", &config.source_branch))?
+    .into_iter()
+    .map(|(path, _)| path)
+    .collect();
+
+    let (target_files, source_files) = match config.layout {
+        Layout::Files => (target_files, source_files),
+        Layout::Directories => (
+            migration_directories(&target_files, None),
+            migration_directories(&source_files, config.target_directory_filter.as_deref()),
+        ),
+    };
+
+    if !target_files.is_empty() {
+        let scheme = config.naming_scheme.as_ref();
+        let anchor = find_anchor(scheme, &target_files, &config.root_directory)?;
+        let existing = parse_scripts(scheme, &target_files, &config.root_directory)?.versioned;
+
+        // Undo scripts reuse the code of the script they revert on purpose,
+        // edits of released scripts are reported separately
+        for script in parse_scripts(scheme, &source_files, &config.root_directory)?
+            .versioned
+            .into_iter()
+            .filter(|script| script.kind != ScriptKind::Undo)
+            .filter(|script| !target_files.iter().any(|file| file == script.file))
+        {
+            if let Some(duplicate) = existing
+                .iter()
+                .find(|other| other.kind != ScriptKind::Undo && other.code == script.code)
+            {
+                report.collisions.push(Collision::Duplicate {
+                    path: script.file.to_string(),
+                    existing: duplicate.file.to_string(),
+                });
+            } else if script.cmp_order(&anchor).is_le() {
+                report.collisions.push(Collision::OutOfOrder {
+                    path: script.file.to_string(),
+                    anchor: anchor.file.to_string(),
+                });
+            }
+        }
+    }
+
+    let plan = process(config, today)?;
+    report.pending = plan.renames;
+    report.edited = plan.edited;
+
+    Ok(report)
+}
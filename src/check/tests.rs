@@ -0,0 +1,174 @@
+use std::path::PathBuf;
+
+use crate::git::tests::internal::{commit_files, create_test_repository};
+use crate::naming::SequenceScheme;
+use crate::process::Configuration;
+
+use super::{check, Collision};
+
+fn configuration(repo_path: PathBuf) -> Configuration {
+    Configuration {
+        repo_path,
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn check_clean() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/1/V20240101.02__file2.txt".to_string()],
+    );
+
+    let report = check(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    assert!(report.is_clean());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn check_pending_renames() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let report = check(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    assert!(!report.is_clean());
+    assert!(report.collisions.is_empty());
+    assert_eq!(report.pending.len(), 1);
+    assert_eq!(
+        report.pending[0].source,
+        "db/migrate/new/01__file_of_my_life.txt"
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn check_collisions() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/1/V20240101.01__file1.txt".to_string(),
+            "db/migrate/1/V20240101.03__file3.txt".to_string(),
+        ],
+        vec![
+            "db/migrate/1/V20240101.01__other.txt".to_string(),
+            "db/migrate/1/V20240101.02__file2.txt".to_string(),
+        ],
+    );
+
+    let report = check(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    assert!(!report.is_clean());
+    assert_eq!(
+        report.collisions,
+        vec![
+            Collision::Duplicate {
+                path: "db/migrate/1/V20240101.01__other.txt".to_string(),
+                existing: "db/migrate/1/V20240101.01__file1.txt".to_string(),
+            },
+            Collision::OutOfOrder {
+                path: "db/migrate/1/V20240101.02__file2.txt".to_string(),
+                anchor: "db/migrate/1/V20240101.03__file3.txt".to_string(),
+            },
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn check_collisions_sequence() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/V0001__init.txt".to_string(),
+            "db/migrate/V0002__users.txt".to_string(),
+        ],
+        vec![
+            "db/migrate/V0002__emails.txt".to_string(),
+            "db/migrate/new/01__orders.txt".to_string(),
+        ],
+    );
+
+    let report = check(
+        Configuration {
+            naming_scheme: Box::new(SequenceScheme::default()),
+            ..configuration(temp_dir.path().to_path_buf())
+        },
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        report.collisions,
+        vec![Collision::Duplicate {
+            path: "db/migrate/V0002__emails.txt".to_string(),
+            existing: "db/migrate/V0002__users.txt".to_string(),
+        }]
+    );
+    assert_eq!(report.pending.len(), 1);
+    assert_eq!(report.pending[0].target, "db/migrate/V0003__orders.txt");
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn check_edited_released_scripts() {
+    let (temp_dir, repo) = create_test_repository(
+        vec![
+            "db/migrate/1/V20240101.01__file1.txt".to_string(),
+            "db/migrate/1/notes.txt".to_string(),
+        ],
+        vec![],
+    );
+    commit_files(
+        &repo,
+        &[
+            "db/migrate/1/V20240101.01__file1.txt",
+            "db/migrate/1/notes.txt",
+        ],
+        "edited",
+        1_700_000_000,
+    );
+
+    let report = check(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    assert!(!report.is_clean());
+    assert!(report.collisions.is_empty());
+    assert!(report.pending.is_empty());
+    assert_eq!(report.edited.len(), 1);
+    assert_eq!(
+        report.edited[0].path,
+        "db/migrate/1/V20240101.01__file1.txt"
+    );
+    assert_eq!(
+        report.edited[0].source_blob,
+        repo.blob(b"edited").unwrap().to_string()
+    );
+
+    temp_dir.close().unwrap();
+}
@@ -1,8 +1,17 @@
 use std::path::PathBuf;
 
-use clap::{Args, Parser};
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
 
-use crate::process::Configuration;
+use crate::companions::Companions;
+use crate::naming::{
+    DateIndexScheme, NamingScheme, OverflowPolicy, PatternScheme, SanitizedScheme, Scheme,
+    SequenceScheme, TimestampScheme,
+};
+use crate::ordering::SourceOrder;
+use crate::process::{Configuration, Layout, DEFAULT_MANIFEST};
+use crate::version_date::{DateSource, Timezone};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -12,52 +21,262 @@ use crate::process::Configuration;
     long_about = "Git merging utility for serialized files"
 )]
 pub struct AppArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[command(flatten)]
     pub opts: Settings,
+
+    /// Project file to read the settings from (defaults to '.fmr.toml' in the repository root)
+    #[arg(long = "config", short = 'c', global = true)]
+    pub config: Option<PathBuf>,
+    /// Named profile from the project file
+    #[arg(long = "profile", short = 'p', global = true)]
+    pub profile: Option<String>,
+
+    /// Move the files in the worktree and stage the renames
+    #[arg(long = "apply")]
+    pub apply: bool,
+    /// Commit the renames on top of the source branch without touching the worktree
+    #[arg(long = "commit", conflicts_with = "apply")]
+    pub commit: bool,
+
+    /// Output format of the rename plan
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Text, global = true)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Fail if the source branch would collide with the target or still needs renames
+    Check,
+    /// Compute the renames and optionally save them for a later apply
+    Plan {
+        /// File to write the plan to as JSON
+        #[arg(long = "out", short = 'o')]
+        out: Option<PathBuf>,
+    },
+    /// Apply a saved plan, refusing if either branch or a staged source changed since it was made
+    Apply {
+        /// Plan written by `plan --out`
+        plan: PathBuf,
+        /// Commit the renames on top of the source branch instead of touching the worktree
+        #[arg(long = "commit")]
+        commit: bool,
+    },
+    /// Move the files of the last applied run back, unless they changed since
+    Undo {
+        /// Number of the run to undo instead of the last one
+        #[arg(long = "run")]
+        run: Option<u32>,
+    },
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// `source -> target` lines
+    Text,
+    /// The whole plan as a JSON document
+    Json,
+}
+
+#[derive(Debug, Default, Args, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Settings {
     /// Path to the repository (defaults to '.')
-    #[arg(long = "repository", short = 'r')]
+    #[arg(long = "repository", short = 'r', global = true)]
+    #[serde(skip)]
     pub repo_path: Option<String>,
     /// Path to script folder inside the repository
-    #[arg(long = "root-dir", short = 'd')]
-    pub root_dir: String,
+    #[arg(long = "root-dir", short = 'd', global = true)]
+    pub root_dir: Option<String>,
 
-    /// Path to script folder inside the repository
-    #[arg(long = "target", short = 't')]
-    pub target_branch: String,
-    /// Path to script folder inside the repository
-    #[arg(long = "source", short = 's')]
-    pub source_branch: String,
+    /// Branch the scripts are merged into
+    #[arg(long = "target", short = 't', global = true)]
+    #[serde(rename = "target")]
+    pub target_branch: Option<String>,
+    /// Branch containing the new scripts
+    #[arg(long = "source", short = 's', global = true)]
+    #[serde(rename = "source")]
+    pub source_branch: Option<String>,
 
     /// Extension filter for the file search
-    #[arg(long = "ext", short = 'e')]
-    pub extension: String,
+    #[arg(long = "ext", short = 'e', global = true)]
+    #[serde(rename = "ext")]
+    pub extension: Option<String>,
 
     /// Path to script folder inside the repository
-    #[arg(long = "target-filter")]
+    #[arg(long = "target-filter", global = true)]
+    #[serde(rename = "target-filter")]
     pub target_directory_filter: Option<String>,
     /// Path to script folder inside the repository
-    #[arg(long = "source-filter")]
+    #[arg(long = "source-filter", global = true)]
+    #[serde(rename = "source-filter")]
     pub source_directory_filter: Option<String>,
+
+    /// Date of the new codes: 'today', 'commit' (source branch tip) or YYYY-MM-DD
+    #[arg(long = "date", global = true)]
+    pub date: Option<DateSource>,
+    /// Timezone the date is taken in: 'local', 'utc' or an offset like +02:00
+    #[arg(long = "timezone", global = true)]
+    pub timezone: Option<Timezone>,
+
+    /// Naming scheme of the target scripts (defaults to date-index)
+    #[arg(long = "scheme", value_enum, global = true)]
+    pub scheme: Option<Scheme>,
+    /// Number of digits of the index within a day (defaults to 2), or of the sequence number (defaults to 4)
+    #[arg(long = "index-width", global = true)]
+    pub index_width: Option<usize>,
+    /// What to do when a day runs out of indexes (defaults to error)
+    #[arg(long = "index-overflow", value_enum, global = true)]
+    pub index_overflow: Option<OverflowPolicy>,
+    /// Regex recognising existing target names, with a name group plus date and index (date-index),
+    /// date and time (timestamp, diesel) or index or version (sequence); versions are whole numbers, not 1.2.3
+    #[arg(long = "target-pattern", global = true)]
+    pub target_pattern: Option<String>,
+    /// Prefix of the sequence number (defaults to V)
+    #[arg(long = "prefix", global = true)]
+    pub prefix: Option<String>,
+    /// Separator between the version and the description (defaults to __, _ for timestamps)
+    #[arg(long, global = true)]
+    pub separator: Option<String>,
+    /// Regex removed from the start of source names before the scheme's prefix (repeatable)
+    #[arg(long = "strip", global = true)]
+    pub strip: Option<Vec<String>>,
+    /// Lowercase the descriptions, replace spaces and transliterate non-ASCII letters
+    #[arg(long = "sanitize", global = true, num_args = 0..=1, default_missing_value = "true")]
+    pub sanitize: Option<bool>,
+    /// Maximum length of the description, without the extension
+    #[arg(long = "max-length", global = true)]
+    pub max_length: Option<usize>,
+    /// Suffixes of companion files sharing one code, e.g. .up/.down (repeatable)
+    #[arg(long = "companions", global = true)]
+    pub companions: Option<Vec<Companions>>,
+    /// Order the source scripts get their codes in (defaults to name)
+    #[arg(long = "order", value_enum, global = true)]
+    pub order: Option<SourceOrder>,
+    /// Manifest file in the source folder listing the scripts, for --order manifest (defaults to .fmr-order)
+    #[arg(long = "manifest", global = true)]
+    pub manifest: Option<String>,
+    /// Message of the --commit commit
+    #[arg(long = "message", short = 'm', global = true)]
+    pub message: Option<String>,
+    /// Author of the --commit commit as 'Name <email>' (defaults to user.name and user.email)
+    #[arg(long = "author", global = true)]
+    pub author: Option<String>,
+    /// Whether a migration is a file or a directory of files (defaults to files)
+    #[arg(long = "layout", value_enum, global = true)]
+    pub layout: Option<Layout>,
 }
 
-impl From<AppArgs> for Configuration {
-    fn from(args: AppArgs) -> Self {
-        Configuration {
-            repo_path: args
-                .opts
-                .repo_path
-                .unwrap_or_else(|| ".".to_string())
-                .into(),
-            root_directory: args.opts.root_dir,
-            target_branch: args.opts.target_branch,
-            source_branch: args.opts.source_branch,
-            extension_filter: Some(args.opts.extension),
-            target_directory_filter: args.opts.target_directory_filter.map(PathBuf::from),
-            source_directory_filter: args.opts.source_directory_filter.map(PathBuf::from),
+impl Settings {
+    /// Fills every value which is not set from `fallback`.
+    pub fn or(self, fallback: Settings) -> Settings {
+        Settings {
+            repo_path: self.repo_path.or(fallback.repo_path),
+            root_dir: self.root_dir.or(fallback.root_dir),
+            target_branch: self.target_branch.or(fallback.target_branch),
+            source_branch: self.source_branch.or(fallback.source_branch),
+            extension: self.extension.or(fallback.extension),
+            target_directory_filter: self
+                .target_directory_filter
+                .or(fallback.target_directory_filter),
+            source_directory_filter: self
+                .source_directory_filter
+                .or(fallback.source_directory_filter),
+            date: self.date.or(fallback.date),
+            timezone: self.timezone.or(fallback.timezone),
+            scheme: self.scheme.or(fallback.scheme),
+            index_width: self.index_width.or(fallback.index_width),
+            index_overflow: self.index_overflow.or(fallback.index_overflow),
+            target_pattern: self.target_pattern.or(fallback.target_pattern),
+            prefix: self.prefix.or(fallback.prefix),
+            separator: self.separator.or(fallback.separator),
+            strip: self.strip.or(fallback.strip),
+            sanitize: self.sanitize.or(fallback.sanitize),
+            max_length: self.max_length.or(fallback.max_length),
+            companions: self.companions.or(fallback.companions),
+            order: self.order.or(fallback.order),
+            manifest: self.manifest.or(fallback.manifest),
+            message: self.message.or(fallback.message),
+            author: self.author.or(fallback.author),
+            layout: self.layout.or(fallback.layout),
         }
     }
 }
+
+impl TryFrom<Settings> for Configuration {
+    type Error = anyhow::Error;
+
+    fn try_from(settings: Settings) -> anyhow::Result<Self> {
+        if settings.index_width == Some(0) {
+            anyhow::bail!("The index width must be at least 1");
+        }
+        if settings.max_length == Some(0) {
+            anyhow::bail!("The maximum description length must be at least 1");
+        }
+        if settings.separator.as_deref() == Some("") {
+            anyhow::bail!("The separator can't be empty");
+        }
+
+        let scheme = settings.scheme.unwrap_or_default();
+        let naming_scheme: Box<dyn NamingScheme> = match scheme {
+            Scheme::DateIndex => Box::new(DateIndexScheme::new(
+                settings.index_width.unwrap_or(2),
+                settings.index_overflow.unwrap_or_default(),
+                settings.separator.as_deref().unwrap_or("__"),
+            )),
+            Scheme::Timestamp => Box::new(TimestampScheme::new(
+                settings.separator.as_deref().unwrap_or("_"),
+            )),
+            Scheme::Diesel => Box::new(TimestampScheme::diesel(
+                settings.separator.as_deref().unwrap_or("_"),
+            )),
+            Scheme::Sequence => Box::new(SequenceScheme::new(
+                settings.prefix.as_deref().unwrap_or("V"),
+                settings.index_width.unwrap_or(4),
+                settings.separator.as_deref().unwrap_or("__"),
+            )),
+        };
+        let sanitize = settings.sanitize.unwrap_or(false);
+        let naming_scheme: Box<dyn NamingScheme> =
+            if settings.strip.is_some() || sanitize || settings.max_length.is_some() {
+                Box::new(SanitizedScheme::new(
+                    settings.strip.as_deref().unwrap_or_default(),
+                    sanitize,
+                    settings.max_length,
+                    naming_scheme,
+                )?)
+            } else {
+                naming_scheme
+            };
+        let naming_scheme: Box<dyn NamingScheme> = match settings.target_pattern {
+            Some(pattern) => Box::new(PatternScheme::new(&pattern, scheme, naming_scheme)?),
+            None => naming_scheme,
+        };
+
+        Ok(Configuration {
+            repo_path: settings.repo_path.unwrap_or_else(|| ".".to_string()).into(),
+            root_directory: settings.root_dir.with_context(|| missing("root-dir"))?,
+            target_branch: settings.target_branch.with_context(|| missing("target"))?,
+            source_branch: settings.source_branch.with_context(|| missing("source"))?,
+            extension_filter: Some(settings.extension.with_context(|| missing("ext"))?),
+            target_directory_filter: settings.target_directory_filter.map(PathBuf::from),
+            source_directory_filter: settings.source_directory_filter.map(PathBuf::from),
+            naming_scheme,
+            companions: settings.companions.unwrap_or_default(),
+            layout: settings.layout.unwrap_or_default(),
+            source_order: settings.order.unwrap_or_default(),
+            manifest: settings
+                .manifest
+                .unwrap_or_else(|| DEFAULT_MANIFEST.to_string()),
+        })
+    }
+}
+
+fn missing(name: &str) -> String {
+    format!(
+        "Missing setting '{0}', pass --{0} or set it in the project file",
+        name
+    )
+}
@@ -0,0 +1,145 @@
+use anyhow::Context;
+use gix::ObjectId;
+use regex::Regex;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::git::{find_entry, open_repository};
+use crate::process::Plan;
+
+#[cfg(test)]
+mod tests;
+
+/// How the rename commit is recorded.
+#[derive(Debug, Clone, Default)]
+pub struct CommitOptions {
+    /// Commit message, a default one names the source branch
+    pub message: Option<String>,
+    /// `Name <email>`, defaults to `user.name` and `user.email` of the repository
+    pub author: Option<String>,
+}
+
+/// Commits the renames of `plan` on top of the tip of `branch` and moves the branch to
+/// the new commit. Only objects and the ref are written, the worktree isn't touched.
+///
+/// Refuses to commit if the branch moved away from the commit the plan was made for, or
+/// if it is checked out, as its index and worktree would still hold the old paths.
+pub fn commit(
+    repo_path: &Path,
+    branch: &str,
+    plan: &Plan,
+    options: &CommitOptions,
+) -> anyhow::Result<ObjectId> {
+    let mut repo = open_repository(repo_path)?;
+
+    let reference = repo
+        .find_reference(branch)
+        .with_context(|| format!("The source has to be a branch to commit to: {}", branch))?;
+    let name = reference.name().to_owned();
+
+    if repo.head_name()?.as_ref() == Some(&name) && repo.work_dir().is_some() {
+        anyhow::bail!(
+            "The source branch {} is checked out, use --apply to rename the scripts in the worktree",
+            branch
+        );
+    }
+
+    let tip = repo
+        .rev_parse_single(name.as_bstr())
+        .with_context(|| format!("Can't resolve the source branch: {}", branch))?
+        .detach();
+    if tip.to_string() != plan.source_commit {
+        anyhow::bail!(
+            "Source branch {} moved to {} since the plan was made at {}",
+            branch,
+            tip,
+            plan.source_commit
+        );
+    }
+
+    let tree_id = repo.find_commit(tip)?.tree_id()?.detach();
+    let mut editor = repo.edit_tree(tree_id)?;
+
+    let mut targets = HashSet::new();
+    for rename in &plan.renames {
+        if !targets.insert(&rename.target) {
+            anyhow::bail!("Several scripts would be renamed to {:?}", rename.target);
+        }
+        let (kind, id) = find_entry(&repo, tree_id, &rename.source)?
+            .with_context(|| format!("Source file is not in {}: {:?}", branch, rename.source))?;
+        if find_entry(&repo, tree_id, &rename.target)?.is_some() {
+            return Err(Error::TargetCollision {
+                path: rename.target.clone(),
+            }
+            .into());
+        }
+
+        editor.remove(rename.source.as_str())?;
+        editor.upsert(rename.target.as_str(), kind, id)?;
+    }
+
+    let new_tree = editor.write()?.detach();
+
+    let (author_name, author_email) = match &options.author {
+        Some(author) => parse_author(author)?,
+        None => {
+            let config = repo.config_snapshot();
+            match (config.string("user.name"), config.string("user.email")) {
+                (Some(name), Some(email)) => (name.to_string(), email.to_string()),
+                _ => anyhow::bail!(
+                    "No author for the commit, pass --author or set user.name and user.email"
+                ),
+            }
+        }
+    };
+
+    // The reflog entry of the branch needs a committer as well, it shouldn't depend on
+    // the identity configured on the host
+    let mut config = repo.config_snapshot_mut();
+    config.set_value(&gix::config::tree::Committer::NAME, author_name.as_str())?;
+    config.set_value(&gix::config::tree::Committer::EMAIL, author_email.as_str())?;
+    config.commit()?;
+
+    let now = chrono::Local::now();
+    let raw_signature = format!(
+        "{} <{}> {} {}",
+        author_name,
+        author_email,
+        now.timestamp(),
+        now.format("%z")
+    );
+    let signature = gix::actor::SignatureRef::from_bytes::<()>(raw_signature.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Invalid author: {:?}", raw_signature))?;
+
+    let message = options
+        .message
+        .clone()
+        .unwrap_or_else(|| format!("Rename the scripts of {} for the merge", branch));
+
+    // Fails if someone else moved the branch in the meantime
+    let commit_id = repo
+        .commit_as(
+            signature,
+            signature,
+            name.as_bstr().to_string().as_str(),
+            message,
+            new_tree,
+            [tip],
+        )
+        .with_context(|| format!("Can't commit the renames on {}", branch))?
+        .detach();
+
+    Ok(commit_id)
+}
+
+/// Splits `Name <email>` into the name and the email.
+fn parse_author(author: &str) -> anyhow::Result<(String, String)> {
+    let pattern = Regex::new(r#"^\s*(.+?)\s*<([^<>]+)>\s*$"#).unwrap();
+    let caps = pattern
+        .captures(author)
+        .with_context(|| format!("Expected an author like 'Name <email>', got: {}", author))?;
+
+    Ok((caps[1].to_string(), caps[2].to_string()))
+}
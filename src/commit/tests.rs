@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+
+use crate::git::tests::internal::{commit_files, create_test_repository};
+use crate::process::{process, Configuration};
+
+use super::{commit, CommitOptions};
+
+fn checkout(repo: &git2::Repository, branch: &str) {
+    repo.set_head(&format!("refs/heads/{}", branch)).unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+}
+
+fn configuration(repo_path: PathBuf) -> Configuration {
+    Configuration {
+        repo_path,
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn commit_renames_without_checkout() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+    let tip = repo.revparse_single("develop").unwrap().id();
+    checkout(&repo, "master");
+
+    let plan = process(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    let options = CommitOptions {
+        message: Some("Rename scripts".to_string()),
+        author: Some("Merge Bot <bot@example.com>".to_string()),
+    };
+    let commit_id = commit(temp_dir.path(), "develop", &plan, &options).unwrap();
+
+    let head = repo
+        .revparse_single("develop")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(head.id().to_string(), commit_id.to_string());
+    assert_eq!(head.parent_id(0).unwrap(), tip);
+    assert_eq!(head.message(), Some("Rename scripts"));
+    assert_eq!(head.author().name(), Some("Merge Bot"));
+    assert_eq!(head.author().email(), Some("bot@example.com"));
+    assert_eq!(head.committer().name(), Some("Merge Bot"));
+
+    // The reflog uses the same identity, whatever is configured on the host
+    let reflog = repo.reflog("refs/heads/develop").unwrap();
+    let entry = reflog.get(0).unwrap();
+    assert_eq!(entry.id_new(), head.id());
+    assert_eq!(entry.committer().name(), Some("Merge Bot"));
+    assert_eq!(entry.committer().email(), Some("bot@example.com"));
+
+    let tree = head.tree().unwrap();
+    assert!(tree
+        .get_path(std::path::Path::new(
+            "db/migrate/1/V20240102.01__file_of_my_life.txt"
+        ))
+        .is_ok());
+    assert!(tree
+        .get_path(std::path::Path::new(
+            "db/migrate/new/01__file_of_my_life.txt"
+        ))
+        .is_err());
+    assert!(tree
+        .get_path(std::path::Path::new("db/migrate/1/V20240101.01__file1.txt"))
+        .is_ok());
+
+    // The checked out branch is untouched
+    assert_eq!(repo.head().unwrap().shorthand(), Some("master"));
+    assert!(!temp_dir
+        .path()
+        .join("db/migrate/1/V20240102.01__file_of_my_life.txt")
+        .exists());
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn commit_refuses_checked_out_branch() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+    let tip = repo.revparse_single("develop").unwrap().id();
+
+    let plan = process(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    let err = commit(
+        temp_dir.path(),
+        "develop",
+        &plan,
+        &CommitOptions {
+            author: Some("Merge Bot <bot@example.com>".to_string()),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "The source branch develop is checked out, use --apply to rename the scripts in the worktree"
+    );
+    assert_eq!(repo.revparse_single("develop").unwrap().id(), tip);
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn commit_refuses_moved_branch() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let plan = process(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    commit_files(
+        &repo,
+        &["db/migrate/new/02__late.txt"],
+        "late",
+        1_700_000_000,
+    );
+    checkout(&repo, "master");
+
+    let err = commit(
+        temp_dir.path(),
+        "develop",
+        &plan,
+        &CommitOptions {
+            author: Some("Merge Bot <bot@example.com>".to_string()),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .starts_with("Source branch develop moved to"));
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn commit_needs_a_branch() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+    let tip = repo.revparse_single("develop").unwrap().id().to_string();
+
+    let mut config = configuration(temp_dir.path().to_path_buf());
+    config.source_branch = tip.clone();
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap();
+
+    let err = commit(temp_dir.path(), &tip, &plan, &CommitOptions::default()).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        format!("The source has to be a branch to commit to: {}", tip)
+    );
+
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,65 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+#[cfg(test)]
+mod tests;
+
+/// Suffixes of files which belong together and share one code, e.g. `.up/.down`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Companions {
+    suffixes: Vec<String>,
+}
+
+impl FromStr for Companions {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let suffixes: Vec<String> = value.split('/').map(String::from).collect();
+
+        if suffixes.len() < 2 || suffixes.iter().any(|suffix| suffix.is_empty()) {
+            anyhow::bail!(
+                "Expected at least two suffixes separated by '/' like .up/.down, got: {}",
+                value
+            );
+        }
+
+        Ok(Companions { suffixes })
+    }
+}
+
+impl TryFrom<String> for Companions {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> anyhow::Result<Self> {
+        value.parse()
+    }
+}
+
+/// Name shared by the companion files, `None` if the file has none of the suffixes.
+///
+/// A suffix is first looked for in front of the extension (`name.up.sql`), then at the
+/// end of the name (`name.rollback.sql`). The longest matching suffix wins.
+pub fn group_stem<'a>(companions: &[Companions], file_name: &'a str) -> Option<&'a str> {
+    let mut suffixes: Vec<&str> = companions
+        .iter()
+        .flat_map(|companions| companions.suffixes.iter().map(String::as_str))
+        .collect();
+    suffixes.sort_by_key(|suffix| std::cmp::Reverse(suffix.len()));
+
+    let without_extension = file_name
+        .rsplit_once('.')
+        .map(|(name, _)| name)
+        .filter(|name| !name.is_empty());
+
+    if let Some(name) = without_extension {
+        if let Some(stem) = suffixes.iter().find_map(|suffix| name.strip_suffix(suffix)) {
+            return Some(stem);
+        }
+    }
+
+    suffixes
+        .iter()
+        .find_map(|suffix| file_name.strip_suffix(suffix))
+}
@@ -0,0 +1,38 @@
+use super::{group_stem, Companions};
+
+fn companions(specs: &[&str]) -> Vec<Companions> {
+    specs.iter().map(|spec| spec.parse().unwrap()).collect()
+}
+
+#[test]
+fn up_down_before_extension() {
+    let companions = companions(&[".up/.down"]);
+
+    assert_eq!(
+        group_stem(&companions, "01__create.up.sql"),
+        Some("01__create")
+    );
+    assert_eq!(
+        group_stem(&companions, "01__create.down.sql"),
+        Some("01__create")
+    );
+    assert_eq!(group_stem(&companions, "01__create.sql"), None);
+}
+
+#[test]
+fn rollback_at_the_end() {
+    let companions = companions(&[".up/.down", ".sql/.rollback.sql"]);
+
+    assert_eq!(group_stem(&companions, "create.sql"), Some("create"));
+    assert_eq!(
+        group_stem(&companions, "create.rollback.sql"),
+        Some("create")
+    );
+    assert_eq!(group_stem(&companions, "create.up.sql"), Some("create"));
+}
+
+#[test]
+fn invalid_spec() {
+    assert!(".up".parse::<Companions>().is_err());
+    assert!(".up/".parse::<Companions>().is_err());
+}
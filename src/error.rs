@@ -1 +1,94 @@
+use std::path::PathBuf;
 
+/// Errors with a stable exit code, so that scripts calling the tool can react on them.
+///
+/// Everything else ends up as a generic failure with [`EXIT_FAILURE`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Can't open the repository: {path:?}")]
+    RepositoryNotFound {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Can't find the branch: {name}")]
+    BranchNotFound {
+        name: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error(
+        "Can't find a script following the naming scheme in the target, rejected: {rejected:?}"
+    )]
+    NoAnchorScript { rejected: Vec<String> },
+    #[error("Can't parse the target name {name:?}: {reason}")]
+    UnparsableTargetName { name: String, reason: String },
+    #[error("Can't find the target folder prefix '{root:?}' in: {path:?}")]
+    PrefixMismatch { root: PathBuf, path: PathBuf },
+    #[error("Target file already exists: {path:?}")]
+    TargetCollision { path: String },
+    #[error("Branch {branch} moved from {planned} to {current} since the plan was made")]
+    StalePlan {
+        branch: String,
+        planned: String,
+        current: String,
+    },
+    #[error("File changed since it was renamed: {path:?}")]
+    ChangedSinceApply { path: String },
+    #[error("Source file changed since the plan was made: {path:?}")]
+    ChangedSincePlan { path: String },
+}
+
+pub const EXIT_FAILURE: u8 = 1;
+pub const EXIT_CHECK_FAILED: u8 = 3;
+
+impl Error {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::RepositoryNotFound { .. } => 10,
+            Error::BranchNotFound { .. } => 11,
+            Error::NoAnchorScript { .. } => 12,
+            Error::UnparsableTargetName { .. } => 13,
+            Error::PrefixMismatch { .. } => 14,
+            Error::TargetCollision { .. } => 15,
+            Error::StalePlan { .. } => 16,
+            Error::ChangedSinceApply { .. } => 17,
+            Error::ChangedSincePlan { .. } => 18,
+        }
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Error::RepositoryNotFound { .. } => {
+                "Run the tool inside a git repository or pass it with --repository"
+            }
+            Error::BranchNotFound { .. } => {
+                "Check the --source and --target names, remote branches may need a fetch first"
+            }
+            Error::NoAnchorScript { .. } => {
+                "Check --root-dir and --target-filter point to the folder with the released scripts"
+            }
+            Error::UnparsableTargetName { .. } => {
+                "Fix or rename the target script so it follows the naming scheme"
+            }
+            Error::PrefixMismatch { .. } => {
+                "--root-dir must be a prefix of every script found in the target"
+            }
+            Error::TargetCollision { .. } => {
+                "Remove the existing file or rerun the plan against the current target branch"
+            }
+            Error::StalePlan { .. } => "Create the plan again and review the new renames",
+            Error::ChangedSinceApply { .. } => {
+                "Restore the file first, or move the scripts back by hand with git mv"
+            }
+            Error::ChangedSincePlan { .. } => {
+                "Commit or stash the change and create the plan again"
+            }
+        }
+    }
+}
+
+/// Finds the first [`Error`] in the context chain of `err`.
+pub fn find_error(err: &anyhow::Error) -> Option<&Error> {
+    err.chain().find_map(|cause| cause.downcast_ref::<Error>())
+}
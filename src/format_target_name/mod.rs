@@ -1,39 +1,63 @@
-use regex::Regex;
 use std::path::Path;
 
+use crate::naming::{Code, NamingScheme, ScriptKind};
+
 #[cfg(test)]
 mod tests;
 
 pub fn format_target_name(
+    scheme: &dyn NamingScheme,
     root: &Path,
     version_path: Option<&str>,
-    order_code: &str,
+    code: &Code,
     filename: &str,
-) -> String {
+) -> anyhow::Result<String> {
     let file_name = Path::new(filename)
         .file_name()
         .unwrap()
         .to_string_lossy()
         .to_string();
 
-    let regex = Regex::new(r#"^\d\d_{2,}"#).unwrap();
+    let (kind, name) = scheme.classify(&file_name);
 
-    let file_name = regex.replace(&file_name, "");
+    // Scripts without a code keep their name
+    let description = match kind {
+        ScriptKind::Repeatable | ScriptKind::Callback => name.to_string(),
+        _ => scheme.strip_prefix(&name).to_string(),
+    };
 
-    Path::join(root, version_path.unwrap_or(""))
-        .join(format!("{}__{}", order_code, file_name))
+    Ok(Path::join(root, version_path.unwrap_or(""))
+        .join(scheme.format_name(kind, code, &description)?)
         .to_string_lossy()
-        .to_string()
+        .to_string())
+}
+
+#[cfg(test)]
+use crate::naming::DateIndexScheme;
+
+#[cfg(test)]
+fn code_20210802_01() -> Code {
+    Code {
+        date: chrono::NaiveDate::from_ymd_opt(2021, 8, 2),
+        index: 1,
+    }
 }
 
 #[test]
 fn format_source_move() {
     let root = Path::new("/mnt/c/Users/josef/source/eurowag/Aequitas");
     let version_path = "Database/Migrates/new";
-    let order_code = "V20210802.01";
+    let order_code = code_20210802_01();
     let filename = "Database/Migrates/new/01__test.sql";
 
-    let result = format_target_name(root, Some(version_path), order_code, filename);
+    let result = format_target_name(
+        &DateIndexScheme::default(),
+        root,
+        Some(version_path),
+        &order_code,
+        filename,
+    )
+    .unwrap();
 
     assert_eq!(
         result,
@@ -45,10 +69,17 @@ fn format_source_move() {
 fn format_source_move_no_source_order() {
     let root = Path::new("/mnt/c/Users/josef/source/eurowag/Aequitas");
     let version_path = "Database/Migrates/new";
-    let order_code = "V20210802.01";
+    let order_code = code_20210802_01();
     let filename = "Database/Migrates/new/test.sql";
 
-    let result = format_target_name(root, Some(version_path), order_code, filename);
+    let result = format_target_name(
+        &DateIndexScheme::default(),
+        root,
+        Some(version_path),
+        &order_code,
+        filename,
+    )
+    .unwrap();
 
     assert_eq!(
         result,
@@ -60,17 +91,31 @@ fn format_source_move_no_source_order() {
 fn format_source_move_version() {
     let root = Path::new("/mnt/c/Users/josef/source/eurowag/Aequitas");
     let version_path = "";
-    let order_code = "V20210802.01";
+    let order_code = code_20210802_01();
     let filename = "Database/Migrates/new/01__test.sql";
 
-    let result = format_target_name(root, Some(version_path), order_code, filename);
+    let result = format_target_name(
+        &DateIndexScheme::default(),
+        root,
+        Some(version_path),
+        &order_code,
+        filename,
+    )
+    .unwrap();
 
     assert_eq!(
         result, "/mnt/c/Users/josef/source/eurowag/Aequitas/V20210802.01__test.sql",
         "Should return the correct path on ordered file"
     );
     let filename = "Database/Migrates/new/test.sql";
-    let result = format_target_name(root, Some(version_path), order_code, filename);
+    let result = format_target_name(
+        &DateIndexScheme::default(),
+        root,
+        Some(version_path),
+        &order_code,
+        filename,
+    )
+    .unwrap();
 
     assert_eq!(
         result, "/mnt/c/Users/josef/source/eurowag/Aequitas/V20210802.01__test.sql",
@@ -81,17 +126,31 @@ fn format_source_move_version() {
 #[test]
 fn format_source_move_version_none() {
     let root = Path::new("/mnt/c/Users/josef/source/eurowag/Aequitas");
-    let order_code = "V20210802.01";
+    let order_code = code_20210802_01();
     let filename = "Database/Migrates/new/01__test.sql";
 
-    let result = format_target_name(root, None, order_code, filename);
+    let result = format_target_name(
+        &DateIndexScheme::default(),
+        root,
+        None,
+        &order_code,
+        filename,
+    )
+    .unwrap();
 
     assert_eq!(
         result, "/mnt/c/Users/josef/source/eurowag/Aequitas/V20210802.01__test.sql",
         "Should return the correct path on ordered file"
     );
     let filename = "Database/Migrates/new/test.sql";
-    let result = format_target_name(root, None, order_code, filename);
+    let result = format_target_name(
+        &DateIndexScheme::default(),
+        root,
+        None,
+        &order_code,
+        filename,
+    )
+    .unwrap();
 
     assert_eq!(
         result, "/mnt/c/Users/josef/source/eurowag/Aequitas/V20210802.01__test.sql",
@@ -1,7 +1,12 @@
 use gix::bstr::ByteSlice; // For handling byte slices in filenames
 use gix::objs::tree::EntryKind; // For differentiating between blobs and trees
 use gix::prelude::FindExt; // For `find_tree`
+use gix::ObjectId;
 
+use anyhow::Context;
+use std::collections::HashMap;
+
+use crate::error::Error;
 use std::path::{Path, PathBuf};
 
 #[cfg(test)]
@@ -13,12 +18,229 @@ pub fn find_files_in_branch(
     extension: Option<&str>,
     directory: Option<&Path>,
 ) -> anyhow::Result<Vec<String>> {
+    let files = find_blobs_in_branch(repo_path, branch, extension, directory)?;
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Lists the files of `branch` together with their blob ids.
+pub fn find_blobs_in_branch(
+    repo_path: &Path,
+    branch: &str,
+    extension: Option<&str>,
+    directory: Option<&Path>,
+) -> anyhow::Result<Vec<(String, ObjectId)>> {
     // Open the repository
-    let repo = gix::open(repo_path)?;
+    let repo = open_repository(repo_path)?;
 
     // Resolve the branch to its latest commit
-    let mut reference = repo.find_reference(branch)?;
-    let commit = reference.peel_to_commit()?;
+    let commit_id = resolve_branch(&repo, branch)?;
+
+    find_files_in_commit(&repo, commit_id, extension, directory)
+}
+
+/// Lists the files of `branch` which were added or modified since its merge-base with `base`,
+/// together with their blob ids.
+pub fn find_new_files_in_branch(
+    repo_path: &Path,
+    branch: &str,
+    base: &str,
+    extension: Option<&str>,
+    directory: Option<&Path>,
+) -> anyhow::Result<Vec<(String, ObjectId)>> {
+    let repo = open_repository(repo_path)?;
+
+    let branch_id = resolve_branch(&repo, branch)?;
+    let base_id = resolve_branch(&repo, base)?;
+
+    let merge_base = repo
+        .merge_base(branch_id, base_id)
+        .with_context(|| format!("Can't find merge base of {} and {}", branch, base))?
+        .detach();
+
+    // Files as they were at the point the branch forked off
+    let existing: HashMap<String, ObjectId> =
+        find_files_in_commit(&repo, merge_base, extension, directory)?
+            .into_iter()
+            .collect();
+
+    let files = find_files_in_commit(&repo, branch_id, extension, directory)?;
+
+    Ok(files
+        .into_iter()
+        .filter(|(path, id)| existing.get(path) != Some(id))
+        .collect())
+}
+
+pub fn open_repository(repo_path: &Path) -> Result<gix::Repository, Error> {
+    gix::open(repo_path).map_err(|err| Error::RepositoryNotFound {
+        path: repo_path.to_path_buf(),
+        source: err.into(),
+    })
+}
+
+/// Resolves any revspec (branch, remote branch, tag, SHA, `HEAD~3`, ...) to a commit id.
+pub fn resolve_commit(repo_path: &Path, spec: &str) -> anyhow::Result<ObjectId> {
+    let repo = open_repository(repo_path)?;
+
+    resolve_branch(&repo, spec)
+}
+
+/// Committer time of the commit `spec` resolves to, in the timezone it was recorded with.
+pub fn commit_time(
+    repo_path: &Path,
+    spec: &str,
+) -> anyhow::Result<chrono::DateTime<chrono::FixedOffset>> {
+    let repo = open_repository(repo_path)?;
+
+    let commit = repo.find_commit(resolve_branch(&repo, spec)?)?;
+    let time = commit.time()?;
+
+    let offset = chrono::FixedOffset::east_opt(time.offset)
+        .with_context(|| format!("Invalid timezone offset of {}: {}", spec, time.offset))?;
+
+    chrono::DateTime::from_timestamp(time.seconds, 0)
+        .map(|utc| utc.with_timezone(&offset))
+        .with_context(|| format!("Invalid commit time of {}: {}", spec, time.seconds))
+}
+
+/// Id of the directory at `path` in the commit `branch` resolves to.
+pub fn find_tree_in_branch(repo_path: &Path, branch: &str, path: &str) -> anyhow::Result<ObjectId> {
+    let repo = open_repository(repo_path)?;
+
+    let commit = repo.find_commit(resolve_branch(&repo, branch)?)?;
+
+    find_entry(&repo, commit.tree_id()?.detach(), path)?
+        .filter(|(kind, _)| *kind == EntryKind::Tree)
+        .map(|(_, id)| id)
+        .with_context(|| format!("Can't find directory {:?} in {}", path, branch))
+}
+
+/// Contents of the file at `path` in the commit `branch` resolves to, `None` if there is none.
+pub fn read_file_in_branch(
+    repo_path: &Path,
+    branch: &str,
+    path: &str,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let repo = open_repository(repo_path)?;
+
+    let commit = repo.find_commit(resolve_branch(&repo, branch)?)?;
+
+    match find_entry(&repo, commit.tree_id()?.detach(), path)? {
+        Some((EntryKind::Blob | EntryKind::BlobExecutable, id)) => {
+            Ok(Some(repo.find_object(id)?.detach().data))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Committer time (seconds since epoch) of the oldest commit on `branch` containing each path.
+///
+/// Follows the first parents from the tip of `branch` until it reaches history it shares
+/// with `base`. Paths which aren't in any of these commits are left out.
+pub fn first_commit_times(
+    repo_path: &Path,
+    branch: &str,
+    base: &str,
+    paths: &[String],
+) -> anyhow::Result<HashMap<String, i64>> {
+    let repo = open_repository(repo_path)?;
+
+    let base_id = resolve_branch(&repo, base)?;
+    let mut next = Some(resolve_branch(&repo, branch)?);
+    let mut times = HashMap::new();
+
+    while let Some(id) = next {
+        // Reached a commit the base already has
+        if repo
+            .merge_base(id, base_id)
+            .is_ok_and(|merge_base| merge_base.detach() == id)
+        {
+            break;
+        }
+
+        let commit = repo.find_commit(id)?;
+        let tree_id = commit.tree_id()?.detach();
+        let time = commit.time()?.seconds;
+
+        for path in paths {
+            if find_entry(&repo, tree_id, path)?.is_some() {
+                times.insert(path.clone(), time);
+            }
+        }
+
+        next = commit.parent_ids().next().map(|parent| parent.detach());
+    }
+
+    Ok(times)
+}
+
+/// Kind and id of the entry at `path` inside the tree `tree_id`.
+pub fn find_entry(
+    repo: &gix::Repository,
+    tree_id: ObjectId,
+    path: &str,
+) -> anyhow::Result<Option<(EntryKind, ObjectId)>> {
+    let mut found = (EntryKind::Tree, tree_id);
+
+    let mut buffer = Vec::new();
+    for component in path.split('/').filter(|component| !component.is_empty()) {
+        if found.0 != EntryKind::Tree {
+            return Ok(None);
+        }
+
+        let tree = repo.objects.find_tree(&found.1, &mut buffer)?;
+        let Some(entry) = tree
+            .entries
+            .iter()
+            .find(|entry| entry.filename.to_str_lossy() == component)
+        else {
+            return Ok(None);
+        };
+
+        found = (entry.mode.kind(), entry.oid.to_owned());
+    }
+
+    Ok(Some(found))
+}
+
+fn resolve_branch(repo: &gix::Repository, branch: &str) -> anyhow::Result<ObjectId> {
+    let id = repo
+        .rev_parse_single(branch)
+        .map_err(|err| Error::BranchNotFound {
+            name: branch.to_string(),
+            source: err.into(),
+        })?;
+    let commit = id
+        .object()?
+        .peel_to_commit()
+        .with_context(|| format!("Not a commit: {}", branch))?;
+
+    Ok(commit.id)
+}
+
+fn find_files_in_commit(
+    repo: &gix::Repository,
+    commit_id: ObjectId,
+    extension: Option<&str>,
+    directory: Option<&Path>,
+) -> anyhow::Result<Vec<(String, ObjectId)>> {
+    Ok(traverse_commit(repo, commit_id, extension, directory)?.matching_files)
+}
+
+/// Files found by a traversal and the amount of trees it had to read for them.
+struct Traversal {
+    matching_files: Vec<(String, ObjectId)>,
+    trees_read: usize,
+}
+
+fn traverse_commit(
+    repo: &gix::Repository,
+    commit_id: ObjectId,
+    extension: Option<&str>,
+    directory: Option<&Path>,
+) -> anyhow::Result<Traversal> {
+    let commit = repo.find_commit(commit_id)?;
     let tree_id = commit.tree_id()?;
 
     // Load the tree object
@@ -26,7 +248,10 @@ pub fn find_files_in_branch(
     let tree = repo.objects.find_tree(&tree_id, &mut buffer)?;
 
     // Collect matching files
-    let mut matching_files = Vec::new();
+    let mut traversal = Traversal {
+        matching_files: Vec::new(),
+        trees_read: 1,
+    };
 
     // Start recursive traversal
     traverse_tree(
@@ -34,11 +259,11 @@ pub fn find_files_in_branch(
         extension,
         directory.map(PathBuf::from),
         String::new(),
-        &mut matching_files,
-        &repo,
+        &mut traversal,
+        repo,
     )?;
 
-    Ok(matching_files)
+    Ok(traversal)
 }
 
 fn traverse_tree(
@@ -46,7 +271,7 @@ fn traverse_tree(
     extension: Option<&str>,
     directory: Option<PathBuf>,
     current_path: String,
-    matching_files: &mut Vec<String>,
+    traversal: &mut Traversal,
     repo: &gix::Repository,
 ) -> anyhow::Result<()> {
     for entry in &tree.entries {
@@ -59,15 +284,25 @@ fn traverse_tree(
 
         match entry.mode.kind() {
             EntryKind::Tree => {
-                // Always recurse into subtrees
+                // Only recurse into subtrees on the way to or inside the directory filter
+                if let Some(ref dir_filter) = directory {
+                    let entry_path_as_path = Path::new(&entry_path);
+                    if !dir_filter.starts_with(entry_path_as_path)
+                        && !entry_path_as_path.starts_with(dir_filter)
+                    {
+                        continue;
+                    }
+                }
+
                 let mut buffer = Vec::new();
                 let subtree = repo.objects.find_tree(entry.oid, &mut buffer)?;
+                traversal.trees_read += 1;
                 traverse_tree(
                     &subtree,
                     extension,
                     directory.clone(),
                     entry_path,
-                    matching_files,
+                    traversal,
                     repo,
                 )?;
             }
@@ -81,10 +316,13 @@ fn traverse_tree(
                 }
 
                 // Check if the file matches the extension
-                if Path::new(&entry_path).extension().map_or(false, |ext| {
-                    extension.is_none() || ext == extension.unwrap()
-                }) {
-                    matching_files.push(entry_path);
+                if Path::new(&entry_path)
+                    .extension()
+                    .is_some_and(|ext| extension.is_none() || ext == extension.unwrap())
+                {
+                    traversal
+                        .matching_files
+                        .push((entry_path, entry.oid.to_owned()));
                 }
             }
             _ => {} // Ignore other types (e.g., symbolic links)
@@ -3,7 +3,7 @@ use std::path::Path;
 use git2::{build::CheckoutBuilder, Repository, Signature};
 use tempdir::TempDir;
 
-use crate::git::find_files_in_branch;
+use crate::git::{find_files_in_branch, find_new_files_in_branch, traverse_commit};
 
 pub fn create_test_repository(
     master_files: Vec<String>,
@@ -20,7 +20,7 @@ pub fn create_test_repository(
         let mut index = repo.index().expect("Failed to get repository index");
         for file in &master_files {
             let file_path = temp_dir.path().join(file);
-            std::fs::create_dir_all(&file_path.parent().unwrap())
+            std::fs::create_dir_all(file_path.parent().unwrap())
                 .expect("Failed to write directories");
             std::fs::write(&file_path, "content").expect("Failed to write file");
             index
@@ -54,7 +54,7 @@ pub fn create_test_repository(
         let mut index = repo.index().expect("Failed to get repository index");
         for file in &develop_files {
             let file_path = temp_dir.path().join(file);
-            std::fs::create_dir_all(&file_path.parent().unwrap())
+            std::fs::create_dir_all(file_path.parent().unwrap())
                 .expect("Failed to write directories");
             std::fs::write(&file_path, "content").expect("Failed to write file");
             index
@@ -85,6 +85,33 @@ fn create_commit(repo: &Repository, message: &str, tree: git2::Tree, parents: &[
         .expect("Failed to create commit");
 }
 
+/// Commits `files` with `content` on top of HEAD at the given time (seconds since epoch).
+pub fn commit_files(repo: &Repository, files: &[&str], content: &str, time: i64) {
+    let work_dir = repo.workdir().expect("Repository has no worktree");
+    let mut index = repo.index().expect("Failed to get repository index");
+    for file in files {
+        let file_path = work_dir.join(file);
+        std::fs::create_dir_all(file_path.parent().unwrap()).expect("Failed to write directories");
+        std::fs::write(&file_path, content).expect("Failed to write file");
+        index
+            .add_path(Path::new(file))
+            .expect("Failed to add file to index");
+    }
+    index.write().expect("Failed to write index");
+
+    let tree_id = index.write_tree().expect("Failed to write tree");
+    let tree = repo.find_tree(tree_id).expect("Failed to find tree");
+    let parent = repo.head().unwrap().peel_to_commit().unwrap();
+    let sig = Signature::new(
+        "script_rename",
+        "script_rename@example.com",
+        &git2::Time::new(time, 0),
+    )
+    .expect("Failed to create signature");
+    repo.commit(Some("HEAD"), &sig, &sig, "Commit", &tree, &[&parent])
+        .expect("Failed to create commit");
+}
+
 #[test]
 fn basic_last_test() {
     let (dir, _) = create_test_repository(
@@ -200,6 +227,71 @@ fn basic_test_deep_folder() {
     dir.close().unwrap();
 }
 
+#[test]
+fn new_files_since_merge_base() {
+    let (dir, _) = create_test_repository(
+        vec![
+            "dir/new/test1.sql".to_string(),
+            "dir/sql/test2.sql".to_string(),
+        ],
+        vec!["dir/new/test3.sql".to_string()],
+    );
+
+    let result = find_new_files_in_branch(
+        dir.path(),
+        "develop",
+        "master",
+        Some("sql"),
+        Some(Path::new("dir/new")),
+    )
+    .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].0, "dir/new/test3.sql".to_string());
+    dir.close().unwrap();
+}
+
+#[test]
+fn traversal_is_pruned_by_directory() {
+    let (dir, repo) = create_test_repository(vec!["db/migrate/new/test.sql".to_string()], vec![]);
+
+    // Put a few thousand unrelated folders next to the scripts
+    let blob = repo.blob(b"content").unwrap();
+    let mut package = repo.treebuilder(None).unwrap();
+    package.insert("file.sql", blob, 0o100644).unwrap();
+    let package = package.write().unwrap();
+
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let mut root = repo.treebuilder(Some(&head.tree().unwrap())).unwrap();
+    for i in 0..2000 {
+        root.insert(format!("pkg{:04}", i), package, 0o040000)
+            .unwrap();
+    }
+    let tree = repo.find_tree(root.write().unwrap()).unwrap();
+    let sig = git2::Signature::now("script_rename", "script_rename@example.com").unwrap();
+    let commit = repo
+        .commit(None, &sig, &sig, "Monorepo", &tree, &[&head])
+        .unwrap();
+
+    let gix_repo = gix::open(dir.path()).unwrap();
+    let commit = gix::ObjectId::from_bytes_or_panic(commit.as_bytes());
+
+    let full = traverse_commit(&gix_repo, commit, Some("sql"), None).unwrap();
+    assert_eq!(full.matching_files.len(), 2001);
+    assert_eq!(full.trees_read, 2004);
+
+    let pruned = traverse_commit(
+        &gix_repo,
+        commit,
+        Some("sql"),
+        Some(Path::new("db/migrate/new")),
+    )
+    .unwrap();
+    assert_eq!(pruned.matching_files.len(), 1);
+    assert_eq!(pruned.trees_read, 4);
+
+    dir.close().unwrap();
+}
+
 // #[test]
 // fn basic_test_no_extension() {
 //     let (dir, _) = create_test_repository(
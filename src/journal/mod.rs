@@ -0,0 +1,131 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// Folder inside the git directory holding one journal per apply run.
+pub const JOURNAL_DIR: &str = "fmr";
+
+/// File in [`JOURNAL_DIR`] with the id of the last recorded run, so that ids of undone
+/// runs aren't given out again.
+pub const LAST_RUN_FILE: &str = "last-run";
+
+/// What one apply run moved, enough to move it back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    /// Number of the run, counting up per repository
+    pub id: u32,
+    /// When the run was applied, RFC 3339
+    pub applied_at: String,
+    pub moves: Vec<Move>,
+}
+
+/// A renamed file or directory with every file it contained.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub source: String,
+    pub target: String,
+    pub files: Vec<MovedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MovedFile {
+    pub source: String,
+    pub target: String,
+    /// Blob id staged for the file
+    pub blob: String,
+}
+
+/// Writes the journal of a new run into `git_dir` and returns it.
+pub fn record(git_dir: &Path, moves: Vec<Move>) -> anyhow::Result<Run> {
+    let dir = git_dir.join(JOURNAL_DIR);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Can't create the journal folder: {:?}", dir))?;
+
+    let id = last_run_id(git_dir)? + 1;
+    let run = Run {
+        id,
+        applied_at: chrono::Local::now().to_rfc3339(),
+        moves,
+    };
+
+    let path = run_path(git_dir, id);
+    std::fs::write(&path, serde_json::to_string_pretty(&run)?)
+        .with_context(|| format!("Can't write the journal: {:?}", path))?;
+
+    let counter = dir.join(LAST_RUN_FILE);
+    std::fs::write(&counter, id.to_string())
+        .with_context(|| format!("Can't write the run counter: {:?}", counter))?;
+
+    Ok(run)
+}
+
+/// Id of the last recorded run, undone or not, 0 before the first one.
+fn last_run_id(git_dir: &Path) -> anyhow::Result<u32> {
+    let counter = git_dir.join(JOURNAL_DIR).join(LAST_RUN_FILE);
+    let recorded = run_ids(git_dir)?.last().copied().unwrap_or(0);
+
+    if !counter.is_file() {
+        return Ok(recorded);
+    }
+
+    let content = std::fs::read_to_string(&counter)
+        .with_context(|| format!("Can't read the run counter: {:?}", counter))?;
+    let last = content
+        .trim()
+        .parse::<u32>()
+        .with_context(|| format!("Invalid run counter: {:?}", counter))?;
+
+    Ok(last.max(recorded))
+}
+
+/// Reads the run `id`, or the latest one.
+pub fn load(git_dir: &Path, id: Option<u32>) -> anyhow::Result<Run> {
+    let id = match id {
+        Some(id) => id,
+        None => *run_ids(git_dir)?.last().context("No applied run to undo")?,
+    };
+
+    let path = run_path(git_dir, id);
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("Can't find the journal of run {}: {:?}", id, path))?;
+
+    serde_json::from_str(&json).with_context(|| format!("Invalid journal: {:?}", path))
+}
+
+/// Drops the journal of a run once it was undone.
+pub fn remove(git_dir: &Path, id: u32) -> anyhow::Result<()> {
+    let path = run_path(git_dir, id);
+
+    std::fs::remove_file(&path).with_context(|| format!("Can't remove the journal: {:?}", path))
+}
+
+/// Ids of the recorded runs, ascending.
+pub fn run_ids(git_dir: &Path) -> anyhow::Result<Vec<u32>> {
+    let dir = git_dir.join(JOURNAL_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut ids = Vec::new();
+    for entry in std::fs::read_dir(&dir).with_context(|| format!("Can't read {:?}", dir))? {
+        let file_name = entry?.file_name();
+        if let Some(id) = file_name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|id| id.parse::<u32>().ok())
+        {
+            ids.push(id);
+        }
+    }
+    ids.sort_unstable();
+
+    Ok(ids)
+}
+
+fn run_path(git_dir: &Path, id: u32) -> PathBuf {
+    git_dir.join(JOURNAL_DIR).join(format!("{}.json", id))
+}
@@ -0,0 +1,42 @@
+use tempdir::TempDir;
+
+use super::{load, record, remove, run_ids, Move, MovedFile};
+
+fn moved(source: &str, target: &str) -> Move {
+    Move {
+        source: source.to_string(),
+        target: target.to_string(),
+        files: vec![MovedFile {
+            source: source.to_string(),
+            target: target.to_string(),
+            blob: "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391".to_string(),
+        }],
+    }
+}
+
+#[test]
+fn runs_count_up_and_latest_is_loaded() {
+    let git_dir = TempDir::new("journal").unwrap();
+
+    assert!(run_ids(git_dir.path()).unwrap().is_empty());
+    assert_eq!(
+        load(git_dir.path(), None).unwrap_err().to_string(),
+        "No applied run to undo"
+    );
+
+    let first = record(git_dir.path(), vec![moved("new/a.sql", "1/V1__a.sql")]).unwrap();
+    let second = record(git_dir.path(), vec![moved("new/b.sql", "1/V2__b.sql")]).unwrap();
+
+    assert_eq!(first.id, 1);
+    assert_eq!(second.id, 2);
+    assert_eq!(load(git_dir.path(), None).unwrap(), second);
+    assert_eq!(load(git_dir.path(), Some(1)).unwrap(), first);
+
+    // An undone run keeps its id, the next run gets a new one
+    remove(git_dir.path(), 2).unwrap();
+    assert_eq!(run_ids(git_dir.path()).unwrap(), vec![1]);
+    assert_eq!(record(git_dir.path(), Vec::new()).unwrap().id, 3);
+    assert!(load(git_dir.path(), Some(2)).is_err());
+
+    git_dir.close().unwrap();
+}
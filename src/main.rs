@@ -1,18 +1,36 @@
+use apply::{apply, undo, verify_checkout};
+use check::{check, Collision};
 use clap::Parser;
-use cli::AppArgs;
+use cli::{AppArgs, Command, OutputFormat};
+use commit::{commit, CommitOptions};
+use error::{find_error, EXIT_CHECK_FAILED, EXIT_FAILURE};
 use parse_path::parse_path;
-use process::process;
+use process::{process, Configuration, Plan, Rename};
+use project_config::resolve_settings;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use version_date::resolve_date;
 
+mod apply;
+mod check;
 mod cli;
+mod commit;
+mod companions;
 mod config;
 mod error;
 mod format_target_name;
 mod git;
+mod journal;
+mod naming;
+mod ordering;
 mod parse_path;
+mod plan_file;
 mod process;
+mod project_config;
+mod version_date;
 
-fn main() {
-    let config = AppArgs::parse();
+fn main() -> ExitCode {
+    let args = AppArgs::parse();
 
     // println!("{:?}", cli);
 
@@ -26,9 +44,233 @@ fn main() {
     //     source_directory_filter: Some(PathBuf::from("Database/Migrates/new")),
     // };
 
-    let result = process(config.into(), chrono::offset::Local::now().date_naive()).unwrap();
+    match run(args) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {:#}", err);
 
-    for (source, target) in result {
-        println!("{} -> {}", source, target);
+            match find_error(&err) {
+                Some(error) => {
+                    eprintln!("Hint: {}", error.hint());
+                    ExitCode::from(error.exit_code())
+                }
+                None => ExitCode::from(EXIT_FAILURE),
+            }
+        }
+    }
+}
+
+fn run(args: AppArgs) -> anyhow::Result<ExitCode> {
+    // The subcommands don't look at the top level mode flags
+    if args.command.is_some() && (args.apply || args.commit) {
+        anyhow::bail!(
+            "--apply and --commit can't be combined with a subcommand, use `apply <plan> [--commit]` for a saved plan"
+        );
+    }
+
+    let mode = if args.apply {
+        Mode::Apply
+    } else if args.commit {
+        Mode::Commit
+    } else {
+        Mode::Plan
+    };
+    let format = args.format;
+
+    let settings = resolve_settings(args.opts, args.config.as_deref(), args.profile.as_deref())?;
+    let date_source = settings.date.clone().unwrap_or_default();
+    let timezone = settings.timezone.clone();
+    let commit_options = CommitOptions {
+        message: settings.message.clone(),
+        author: settings.author.clone(),
+    };
+
+    // A saved plan carries everything else
+    let command = match args.command {
+        Some(Command::Apply { plan, commit }) => {
+            let repo_path = PathBuf::from(settings.repo_path.as_deref().unwrap_or("."));
+            let mode = if commit { Mode::Commit } else { Mode::Apply };
+            return run_apply(&repo_path, &plan, format, mode, commit_options);
+        }
+        Some(Command::Undo { run }) => {
+            let repo_path = PathBuf::from(settings.repo_path.as_deref().unwrap_or("."));
+            return run_undo(&repo_path, run, format);
+        }
+        command => command,
+    };
+
+    let config: Configuration = settings.try_into()?;
+    let today = resolve_date(&date_source, timezone.as_ref(), &config)?;
+
+    match command {
+        Some(Command::Check) => run_check(config, today, format),
+        Some(Command::Plan { out }) => run_process(
+            config,
+            today,
+            format,
+            Mode::Plan,
+            commit_options,
+            out.as_deref(),
+        ),
+        Some(Command::Apply { .. } | Command::Undo { .. }) => unreachable!("handled above"),
+        None => run_process(config, today, format, mode, commit_options, None),
+    }
+}
+
+/// What happens with the planned renames.
+enum Mode {
+    Plan,
+    Apply,
+    Commit,
+}
+
+fn run_process(
+    config: Configuration,
+    today: chrono::NaiveDate,
+    format: OutputFormat,
+    mode: Mode,
+    commit_options: CommitOptions,
+    out: Option<&Path>,
+) -> anyhow::Result<ExitCode> {
+    let repo_path = config.repo_path.clone();
+
+    let plan = process(config, today)?;
+
+    if let Some(out) = out {
+        plan_file::save(&plan, out)?;
+        eprintln!("Plan written to {:?}", out);
+    }
+
+    finish(&repo_path, &plan, format, mode, &commit_options)
+}
+
+fn run_apply(
+    repo_path: &Path,
+    plan_path: &Path,
+    format: OutputFormat,
+    mode: Mode,
+    commit_options: CommitOptions,
+) -> anyhow::Result<ExitCode> {
+    let plan = plan_file::load(plan_path)?;
+    plan_file::verify(repo_path, &plan)?;
+    if matches!(mode, Mode::Apply) {
+        verify_checkout(repo_path, &plan)?;
+    }
+
+    finish(repo_path, &plan, format, mode, &commit_options)
+}
+
+fn run_undo(repo_path: &Path, run: Option<u32>, format: OutputFormat) -> anyhow::Result<ExitCode> {
+    let run = undo(repo_path, run)?;
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&run)?),
+        OutputFormat::Text => {
+            for moved in run.moves.iter().rev() {
+                println!("Moved back {} -> {}", moved.target, moved.source);
+            }
+        }
+    }
+    eprintln!("Undid run {} applied at {}", run.id, run.applied_at);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Carries out the plan according to `mode` and prints it.
+fn finish(
+    repo_path: &Path,
+    plan: &Plan,
+    format: OutputFormat,
+    mode: Mode,
+    commit_options: &CommitOptions,
+) -> anyhow::Result<ExitCode> {
+    let (moved, committed) = match mode {
+        Mode::Plan => (None, None),
+        Mode::Apply => (Some(apply(repo_path, &plan.renames)?), None),
+        Mode::Commit => (
+            None,
+            Some(commit(
+                repo_path,
+                &plan.source_branch,
+                plan,
+                commit_options,
+            )?),
+        ),
+    };
+
+    print_plan(plan, moved, format)?;
+
+    if let Some(commit_id) = committed {
+        eprintln!("Committed {} on {}", commit_id, plan.source_branch);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_plan(plan: &Plan, moved: Option<Vec<Rename>>, format: OutputFormat) -> anyhow::Result<()> {
+    match (format, moved) {
+        (OutputFormat::Json, _) => {
+            println!("{}", serde_json::to_string_pretty(plan)?);
+        }
+        (OutputFormat::Text, Some(moved)) => {
+            for rename in moved {
+                println!("Moved {} -> {}", rename.source, rename.target);
+            }
+        }
+        (OutputFormat::Text, None) => {
+            for rename in &plan.renames {
+                println!("{} -> {}", rename.source, rename.target);
+            }
+        }
+    }
+
+    if format == OutputFormat::Text {
+        for merged in &plan.merged {
+            println!("Already merged {} as {}", merged.source, merged.target);
+        }
+        for edited in &plan.edited {
+            println!(
+                "Released script edited on the source branch: {}",
+                edited.path
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_check(
+    config: Configuration,
+    today: chrono::NaiveDate,
+    format: OutputFormat,
+) -> anyhow::Result<ExitCode> {
+    let report = check(config, today)?;
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Text => {
+            for collision in &report.collisions {
+                match collision {
+                    Collision::Duplicate { path, existing } => {
+                        println!("{} uses the same code as {}", path, existing)
+                    }
+                    Collision::OutOfOrder { path, anchor } => {
+                        println!("{} is not newer than {}", path, anchor)
+                    }
+                }
+            }
+            for rename in &report.pending {
+                println!("{} needs to be renamed to {}", rename.source, rename.target);
+            }
+            for edited in &report.edited {
+                println!("{} was released and must not be edited", edited.path);
+            }
+        }
+    }
+
+    if report.is_clean() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_CHECK_FAILED))
     }
 }
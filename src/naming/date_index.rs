@@ -0,0 +1,243 @@
+use std::borrow::Cow;
+
+use anyhow::Context;
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{Code, NamingScheme, ParsedName, ScriptKind};
+use crate::error::Error;
+
+/// Flyway callback events, `beforeMigrate.sql` or `afterMigrate__log.sql` run on them.
+const CALLBACK_EVENTS: &[&str] = &[
+    "beforeMigrate",
+    "beforeRepeatables",
+    "beforeEachMigrate",
+    "beforeEachMigrateStatement",
+    "afterEachMigrateStatement",
+    "afterEachMigrateStatementError",
+    "afterEachMigrate",
+    "afterEachMigrateError",
+    "afterMigrate",
+    "afterMigrateApplied",
+    "afterVersioned",
+    "afterMigrateError",
+    "beforeUndo",
+    "beforeEachUndo",
+    "beforeEachUndoStatement",
+    "afterEachUndoStatement",
+    "afterEachUndoStatementError",
+    "afterEachUndo",
+    "afterEachUndoError",
+    "afterUndo",
+    "afterUndoError",
+    "beforeClean",
+    "afterClean",
+    "afterCleanError",
+    "beforeInfo",
+    "afterInfo",
+    "afterInfoError",
+    "beforeValidate",
+    "afterValidate",
+    "afterValidateError",
+    "beforeBaseline",
+    "afterBaseline",
+    "afterBaselineError",
+    "beforeRepair",
+    "afterRepair",
+    "afterRepairError",
+    "beforeCreateSchema",
+    "beforeConnect",
+    "afterConnect",
+];
+
+/// What happens when a day runs out of indexes for the configured width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Keep counting with more digits, e.g. `.99` is followed by `.100`
+    Widen,
+    /// Continue with index 1 on the following day
+    NextDay,
+    /// Refuse to generate the code
+    #[default]
+    Error,
+}
+
+/// The `V{date}.{index}__{name}` scheme, the index is zero padded to `width` digits.
+///
+/// Understands the other Flyway prefixes as well: `U` and `B` scripts carry a code,
+/// `R__` and callbacks like `beforeMigrate.sql` don't. The `__` separator is configurable.
+pub struct DateIndexScheme {
+    pattern: Regex,
+    repeatable: Regex,
+    undo_or_baseline: Regex,
+    callback: Regex,
+    source_prefix: Regex,
+    separator: String,
+    width: usize,
+    overflow: OverflowPolicy,
+}
+
+impl DateIndexScheme {
+    pub fn new(width: usize, overflow: OverflowPolicy, separator: &str) -> Self {
+        let sep = regex::escape(separator);
+
+        Self {
+            pattern: Regex::new(&format!(r#"^([VUB])(\d{{8}})\.(\d+){}(.+)$"#, sep)).unwrap(),
+            repeatable: Regex::new(&format!(r#"^R{}(.+)$"#, sep)).unwrap(),
+            undo_or_baseline: Regex::new(&format!(
+                r#"^([UB])(?:(\d+(?:\.\d+)*{sep}.+)|{sep}(.+))$"#
+            ))
+            .unwrap(),
+            callback: Regex::new(&format!(
+                r#"^({})({}.+)?\.[^.]+$"#,
+                CALLBACK_EVENTS.join("|"),
+                sep
+            ))
+            .unwrap(),
+            source_prefix: Regex::new(r#"^\d\d_{2,}"#).unwrap(),
+            separator: separator.to_string(),
+            width,
+            overflow,
+        }
+    }
+
+    /// Highest index which fits into the configured width.
+    fn max_index(&self) -> u64 {
+        10u64.saturating_pow(self.width as u32) - 1
+    }
+}
+
+impl Default for DateIndexScheme {
+    fn default() -> Self {
+        Self::new(2, OverflowPolicy::default(), "__")
+    }
+}
+
+impl NamingScheme for DateIndexScheme {
+    fn parse(&self, file_name: &str) -> Result<Option<ParsedName>, Error> {
+        let Some(caps) = self.pattern.captures(file_name) else {
+            return Ok(None);
+        };
+
+        let kind = match &caps[1] {
+            "U" => ScriptKind::Undo,
+            "B" => ScriptKind::Baseline,
+            _ => ScriptKind::Versioned,
+        };
+        let date = chrono::NaiveDate::parse_from_str(&caps[2], "%Y%m%d").map_err(|err| {
+            Error::UnparsableTargetName {
+                name: file_name.to_string(),
+                reason: format!("invalid date {}: {}", &caps[2], err),
+            }
+        })?;
+        let index = caps[3]
+            .parse::<u64>()
+            .map_err(|err| Error::UnparsableTargetName {
+                name: file_name.to_string(),
+                reason: format!("invalid index {}: {}", &caps[3], err),
+            })?;
+
+        Ok(Some(ParsedName {
+            kind,
+            code: Code {
+                date: Some(date),
+                index,
+            },
+            description: caps[4].to_string(),
+        }))
+    }
+
+    fn classify<'a>(&self, file_name: &'a str) -> (ScriptKind, Cow<'a, str>) {
+        if let Some(caps) = self.repeatable.captures(file_name) {
+            let name = caps.get(1).unwrap().as_str();
+            return (ScriptKind::Repeatable, Cow::Borrowed(name));
+        }
+        if self.callback.is_match(file_name) {
+            return (ScriptKind::Callback, Cow::Borrowed(file_name));
+        }
+        // `U01__name` and `U__name`, but neither `Update_name` nor `U1_name`, the same for `B`
+        if let Some(caps) = self.undo_or_baseline.captures(file_name) {
+            let kind = match &caps[1] {
+                "U" => ScriptKind::Undo,
+                _ => ScriptKind::Baseline,
+            };
+            let name = caps.get(2).or_else(|| caps.get(3)).unwrap().as_str();
+            return (kind, Cow::Borrowed(name));
+        }
+
+        (ScriptKind::Versioned, Cow::Borrowed(file_name))
+    }
+
+    fn next_code(&self, previous: &Code, today: chrono::NaiveDate) -> anyhow::Result<Code> {
+        let date = previous
+            .date
+            .with_context(|| format!("Code without a date: {:?}", previous))?;
+
+        if today > date {
+            return Ok(Code {
+                date: Some(today),
+                index: 1,
+            });
+        }
+
+        let index = previous.index + 1;
+
+        if index <= self.max_index() {
+            return Ok(Code {
+                date: Some(date),
+                index,
+            });
+        }
+
+        match self.overflow {
+            OverflowPolicy::Widen => Ok(Code {
+                date: Some(date),
+                index,
+            }),
+            OverflowPolicy::NextDay => Ok(Code {
+                date: Some(date.succ_opt().context("Date out of range")?),
+                index: 1,
+            }),
+            OverflowPolicy::Error => anyhow::bail!(
+                "No index left on {} for width {}, choose another overflow policy",
+                date,
+                self.width
+            ),
+        }
+    }
+
+    fn strip_prefix<'a>(&self, file_name: &'a str) -> Cow<'a, str> {
+        self.source_prefix.replace(file_name, "")
+    }
+
+    fn format_name(
+        &self,
+        kind: ScriptKind,
+        code: &Code,
+        description: &str,
+    ) -> anyhow::Result<String> {
+        let prefix = match kind {
+            ScriptKind::Versioned => "V",
+            ScriptKind::Undo => "U",
+            ScriptKind::Baseline => "B",
+            ScriptKind::Repeatable => return Ok(format!("R{}{}", self.separator, description)),
+            ScriptKind::Callback => return Ok(description.to_string()),
+        };
+        let date = code
+            .date
+            .with_context(|| format!("Code without a date: {:?}", code))?
+            .format("%Y%m%d");
+
+        Ok(format!(
+            "{}{}.{:0width$}{}{}",
+            prefix,
+            date,
+            code.index,
+            self.separator,
+            description,
+            width = self.width
+        ))
+    }
+}
@@ -0,0 +1,98 @@
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+mod date_index;
+mod pattern;
+mod sanitize;
+mod sequence;
+mod timestamp;
+
+pub use date_index::{DateIndexScheme, OverflowPolicy};
+pub use pattern::PatternScheme;
+pub use sanitize::SanitizedScheme;
+pub use sequence::SequenceScheme;
+pub use timestamp::TimestampScheme;
+
+#[cfg(test)]
+mod tests;
+
+/// Naming schemes which can be selected in the settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scheme {
+    /// `V{date}.{index}__{name}`, see [`DateIndexScheme`]
+    #[default]
+    DateIndex,
+    /// `{YYYYMMDDHHMMSS}_{name}`, see [`TimestampScheme`]
+    Timestamp,
+    /// `{YYYY-MM-DD-HHMMSS}_{name}` of Diesel, see [`TimestampScheme::diesel`]
+    Diesel,
+    /// `{prefix}{number}__{name}`, see [`SequenceScheme`]
+    Sequence,
+}
+
+/// Ordering code of a script, as understood by a [`NamingScheme`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Code {
+    /// Date part of the code, if the scheme has one
+    pub date: Option<chrono::NaiveDate>,
+    /// Sequence number, within the date if there is one
+    pub index: u64,
+}
+
+/// Kind of a script, following the Flyway prefixes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptKind {
+    /// `V<version>__`, gets a new code
+    Versioned,
+    /// `U<version>__`, shares the code of its versioned script
+    Undo,
+    /// `B<version>__`
+    Baseline,
+    /// `R__`, moved without a code
+    Repeatable,
+    /// `beforeMigrate.sql` and friends, moved without a code
+    Callback,
+}
+
+/// Existing target file name split into its parts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedName {
+    pub kind: ScriptKind,
+    pub code: Code,
+    pub description: String,
+}
+
+/// Describes how the ordered script names look and how the next one is produced.
+pub trait NamingScheme {
+    /// Parses an existing target file name, `None` if it doesn't follow the scheme.
+    ///
+    /// Names which look like they follow the scheme but can't be parsed are an error.
+    fn parse(&self, file_name: &str) -> Result<Option<ParsedName>, Error>;
+
+    /// Tells the kind of a source file, together with its name without the kind marker.
+    fn classify<'a>(&self, file_name: &'a str) -> (ScriptKind, Cow<'a, str>) {
+        (ScriptKind::Versioned, Cow::Borrowed(file_name))
+    }
+
+    /// Produces the code which directly follows `previous`.
+    fn next_code(&self, previous: &Code, today: chrono::NaiveDate) -> anyhow::Result<Code>;
+
+    /// Turns a source file name into a description, removing the ordering prefix developers put on it.
+    fn strip_prefix<'a>(&self, file_name: &'a str) -> Cow<'a, str>;
+
+    /// Builds the target file name from a code and a description, kinds without a code ignore it.
+    ///
+    /// Fails for a code the scheme can't write, e.g. one without a date for a dated scheme.
+    fn format_name(
+        &self,
+        kind: ScriptKind,
+        code: &Code,
+        description: &str,
+    ) -> anyhow::Result<String>;
+}
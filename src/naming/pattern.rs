@@ -0,0 +1,157 @@
+use std::borrow::Cow;
+
+use chrono::Timelike;
+use clap::ValueEnum;
+use regex::Regex;
+
+use super::{Code, NamingScheme, ParsedName, Scheme, ScriptKind};
+use crate::error::Error;
+
+/// Recognises existing target names with a custom regex, new names come from `inner`.
+/// Names the regex doesn't match are parsed by `inner`, so a folder may mix both.
+///
+/// The regex needs a `name` group and the groups making up a code of the selected
+/// [`Scheme`]: `date` and `index` for date-index, `date` and `time` (`HHMMSS`) for
+/// timestamp and diesel, and `index` or `version` for sequence. Any separators inside the
+/// date and the time are ignored, e.g. `v(?<date>\d{4}_\d\d_\d\d)_(?<index>\d+)-(?<name>.+)`
+/// matches `v2024_01_01_03-name.sql`. A `version` is a whole number, dotted versions like
+/// `1.2.3` aren't supported.
+pub struct PatternScheme {
+    pattern: Regex,
+    inner: Box<dyn NamingScheme>,
+}
+
+impl PatternScheme {
+    pub fn new(
+        pattern: &str,
+        scheme: Scheme,
+        inner: Box<dyn NamingScheme>,
+    ) -> anyhow::Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|err| anyhow::anyhow!("Invalid target pattern {:?}: {}", pattern, err))?;
+
+        let has = |group: &str| pattern.capture_names().flatten().any(|name| name == group);
+        let scheme_name = scheme
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+
+        if !has("name") {
+            anyhow::bail!("The target pattern needs a (?<name>...) group: {}", pattern);
+        }
+
+        let (required, forbidden): (&[&str], &[&str]) = match scheme {
+            Scheme::DateIndex => (&["date", "index"], &["time", "version"]),
+            Scheme::Timestamp | Scheme::Diesel => (&["date", "time"], &["index", "version"]),
+            Scheme::Sequence => {
+                if has("index") && has("version") {
+                    anyhow::bail!(
+                        "The target pattern can't have both an (?<index>...) and a (?<version>...) group: {}",
+                        pattern
+                    );
+                }
+                if !has("index") && !has("version") {
+                    anyhow::bail!(
+                        "The target pattern needs an (?<index>...) or a (?<version>...) group for the sequence scheme: {}",
+                        pattern
+                    );
+                }
+                (&[], &["date", "time"])
+            }
+        };
+
+        if let Some(group) = required.iter().find(|group| !has(group)) {
+            anyhow::bail!(
+                "The target pattern needs a (?<{}>...) group for the {} scheme: {}",
+                group,
+                scheme_name,
+                pattern
+            );
+        }
+        if let Some(group) = forbidden.iter().find(|group| has(group)) {
+            anyhow::bail!(
+                "The target pattern can't have a (?<{}>...) group with the {} scheme: {}",
+                group,
+                scheme_name,
+                pattern
+            );
+        }
+
+        Ok(Self { pattern, inner })
+    }
+}
+
+impl NamingScheme for PatternScheme {
+    fn parse(&self, file_name: &str) -> Result<Option<ParsedName>, Error> {
+        let Some(caps) = self.pattern.captures(file_name) else {
+            // Names the tool wrote itself follow the inner scheme
+            return self.inner.parse(file_name);
+        };
+        let unparsable = |reason: String| Error::UnparsableTargetName {
+            name: file_name.to_string(),
+            reason,
+        };
+
+        let digits =
+            |text: &str| -> String { text.chars().filter(|c| c.is_ascii_digit()).collect() };
+
+        let date = match caps.name("date") {
+            Some(date) => {
+                let date = chrono::NaiveDate::parse_from_str(&digits(date.as_str()), "%Y%m%d")
+                    .map_err(|err| {
+                        unparsable(format!("invalid date {}: {}", date.as_str(), err))
+                    })?;
+                Some(date)
+            }
+            None => None,
+        };
+
+        // The timestamp scheme counts seconds since midnight
+        let index = match caps.name("time") {
+            Some(time) => {
+                let time = chrono::NaiveTime::parse_from_str(&digits(time.as_str()), "%H%M%S")
+                    .map_err(|err| {
+                        unparsable(format!("invalid time {}: {}", time.as_str(), err))
+                    })?;
+                time.num_seconds_from_midnight() as u64
+            }
+            None => {
+                let index = caps
+                    .name("index")
+                    .or_else(|| caps.name("version"))
+                    .map(|index| index.as_str())
+                    .unwrap_or_default();
+                index
+                    .parse::<u64>()
+                    .map_err(|err| unparsable(format!("invalid index {}: {}", index, err)))?
+            }
+        };
+
+        Ok(Some(ParsedName {
+            kind: ScriptKind::Versioned,
+            code: Code { date, index },
+            description: caps["name"].to_string(),
+        }))
+    }
+
+    fn classify<'a>(&self, file_name: &'a str) -> (ScriptKind, Cow<'a, str>) {
+        self.inner.classify(file_name)
+    }
+
+    fn next_code(&self, previous: &Code, today: chrono::NaiveDate) -> anyhow::Result<Code> {
+        self.inner.next_code(previous, today)
+    }
+
+    fn strip_prefix<'a>(&self, file_name: &'a str) -> Cow<'a, str> {
+        self.inner.strip_prefix(file_name)
+    }
+
+    fn format_name(
+        &self,
+        kind: ScriptKind,
+        code: &Code,
+        description: &str,
+    ) -> anyhow::Result<String> {
+        self.inner.format_name(kind, code, description)
+    }
+}
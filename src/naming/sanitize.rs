@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use super::{Code, NamingScheme, ParsedName, ScriptKind};
+use crate::error::Error;
+
+/// Cleans the descriptions produced by `inner`: strips the configured prefixes first and
+/// optionally normalises the rest. The extension is kept as it is.
+pub struct SanitizedScheme {
+    strip: Vec<Regex>,
+    /// Lowercase, spaces to underscores and non-ASCII transliterated
+    normalize: bool,
+    /// Maximum length of the description without the extension
+    max_length: Option<usize>,
+    inner: Box<dyn NamingScheme>,
+}
+
+impl SanitizedScheme {
+    pub fn new(
+        strip: &[String],
+        normalize: bool,
+        max_length: Option<usize>,
+        inner: Box<dyn NamingScheme>,
+    ) -> anyhow::Result<Self> {
+        let strip = strip
+            .iter()
+            .map(|pattern| {
+                // A prefix, even if the pattern isn't anchored itself
+                Regex::new(&format!("^(?:{})", pattern))
+                    .map_err(|err| anyhow::anyhow!("Invalid strip pattern {:?}: {}", pattern, err))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            strip,
+            normalize,
+            max_length,
+            inner,
+        })
+    }
+
+    fn sanitize(&self, description: &str) -> String {
+        let (stem, extension) = match description.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+            _ => (description, None),
+        };
+
+        let mut stem = if self.normalize {
+            normalize(stem)
+        } else {
+            stem.to_string()
+        };
+
+        if let Some(max_length) = self.max_length {
+            stem = stem.chars().take(max_length).collect();
+            stem.truncate(stem.trim_end_matches(['_', '-', '.']).len());
+        }
+
+        match extension {
+            Some(extension) => format!("{}.{}", stem, extension),
+            None => stem,
+        }
+    }
+}
+
+/// Lowercases, turns whitespace into underscores and transliterates non-ASCII letters,
+/// anything without an ASCII form becomes an underscore.
+fn normalize(value: &str) -> String {
+    let mut normalized = String::with_capacity(value.len());
+
+    for c in value.nfd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            c if c.is_whitespace() => normalized.push('_'),
+            c if c.is_ascii() => normalized.push(c.to_ascii_lowercase()),
+            'ß' => normalized.push_str("ss"),
+            'æ' | 'Æ' => normalized.push_str("ae"),
+            'œ' | 'Œ' => normalized.push_str("oe"),
+            'ø' | 'Ø' => normalized.push('o'),
+            'đ' | 'Đ' => normalized.push('d'),
+            'ł' | 'Ł' => normalized.push('l'),
+            'þ' | 'Þ' => normalized.push_str("th"),
+            _ => normalized.push('_'),
+        }
+    }
+
+    normalized
+}
+
+impl NamingScheme for SanitizedScheme {
+    fn parse(&self, file_name: &str) -> Result<Option<ParsedName>, Error> {
+        self.inner.parse(file_name)
+    }
+
+    fn classify<'a>(&self, file_name: &'a str) -> (ScriptKind, Cow<'a, str>) {
+        self.inner.classify(file_name)
+    }
+
+    fn next_code(&self, previous: &Code, today: chrono::NaiveDate) -> anyhow::Result<Code> {
+        self.inner.next_code(previous, today)
+    }
+
+    fn strip_prefix<'a>(&self, file_name: &'a str) -> Cow<'a, str> {
+        let mut name = file_name.to_string();
+        for pattern in &self.strip {
+            name = pattern.replace(&name, "").to_string();
+        }
+
+        let name = self.inner.strip_prefix(&name).to_string();
+
+        Cow::Owned(self.sanitize(&name))
+    }
+
+    fn format_name(
+        &self,
+        kind: ScriptKind,
+        code: &Code,
+        description: &str,
+    ) -> anyhow::Result<String> {
+        self.inner.format_name(kind, code, description)
+    }
+}
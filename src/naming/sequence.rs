@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use super::{Code, NamingScheme, ParsedName, ScriptKind};
+use crate::error::Error;
+
+/// The `{prefix}{number}{separator}{name}` scheme without a date, e.g. `V0001__name.sql`.
+///
+/// The number is zero padded to `width` digits and grows wider once it runs out of them.
+pub struct SequenceScheme {
+    pattern: Regex,
+    source_prefix: Regex,
+    prefix: String,
+    separator: String,
+    width: usize,
+}
+
+impl SequenceScheme {
+    pub fn new(prefix: &str, width: usize, separator: &str) -> Self {
+        Self {
+            pattern: Regex::new(&format!(
+                r#"^{}(\d+){}(.+)$"#,
+                regex::escape(prefix),
+                regex::escape(separator)
+            ))
+            .unwrap(),
+            source_prefix: Regex::new(r#"^\d\d_{2,}"#).unwrap(),
+            prefix: prefix.to_string(),
+            separator: separator.to_string(),
+            width,
+        }
+    }
+}
+
+impl Default for SequenceScheme {
+    fn default() -> Self {
+        Self::new("V", 4, "__")
+    }
+}
+
+impl NamingScheme for SequenceScheme {
+    fn parse(&self, file_name: &str) -> Result<Option<ParsedName>, Error> {
+        let Some(caps) = self.pattern.captures(file_name) else {
+            return Ok(None);
+        };
+
+        let index = caps[1]
+            .parse::<u64>()
+            .map_err(|err| Error::UnparsableTargetName {
+                name: file_name.to_string(),
+                reason: format!("invalid number {}: {}", &caps[1], err),
+            })?;
+
+        Ok(Some(ParsedName {
+            kind: ScriptKind::Versioned,
+            code: Code { date: None, index },
+            description: caps[2].to_string(),
+        }))
+    }
+
+    fn next_code(&self, previous: &Code, _today: chrono::NaiveDate) -> anyhow::Result<Code> {
+        Ok(Code {
+            date: None,
+            index: previous.index + 1,
+        })
+    }
+
+    fn strip_prefix<'a>(&self, file_name: &'a str) -> Cow<'a, str> {
+        self.source_prefix.replace(file_name, "")
+    }
+
+    fn format_name(
+        &self,
+        kind: ScriptKind,
+        code: &Code,
+        description: &str,
+    ) -> anyhow::Result<String> {
+        Ok(match kind {
+            ScriptKind::Repeatable | ScriptKind::Callback => description.to_string(),
+            _ => format!(
+                "{}{:0width$}{}{}",
+                self.prefix,
+                code.index,
+                self.separator,
+                description,
+                width = self.width
+            ),
+        })
+    }
+}
@@ -0,0 +1,672 @@
+use super::{
+    Code, DateIndexScheme, NamingScheme, OverflowPolicy, ParsedName, PatternScheme,
+    SanitizedScheme, Scheme, ScriptKind, SequenceScheme, TimestampScheme,
+};
+
+fn date(year: i32, month: u32, day: u32) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::from_ymd_opt(year, month, day)
+}
+
+#[test]
+fn date_index_parse() {
+    let scheme = DateIndexScheme::default();
+
+    assert_eq!(
+        scheme.parse("V20240101.02__file1.sql").unwrap(),
+        Some(ParsedName {
+            kind: ScriptKind::Versioned,
+            code: Code {
+                date: date(2024, 1, 1),
+                index: 2
+            },
+            description: "file1.sql".to_string()
+        })
+    );
+    assert_eq!(scheme.parse("V20240101__file1.sql").unwrap(), None);
+    assert_eq!(scheme.parse("readme.sql").unwrap(), None);
+}
+
+#[test]
+fn date_index_parse_invalid_date() {
+    let scheme = DateIndexScheme::default();
+
+    let err = scheme.parse("V20241340.01__file1.sql").unwrap_err();
+
+    assert_eq!(err.exit_code(), 13);
+    assert!(err.to_string().starts_with(
+        "Can't parse the target name \"V20241340.01__file1.sql\": invalid date 20241340"
+    ));
+}
+
+#[test]
+fn date_index_next_code() -> anyhow::Result<()> {
+    let scheme = DateIndexScheme::default();
+    let previous = Code {
+        date: date(2024, 1, 1),
+        index: 2,
+    };
+
+    assert_eq!(
+        scheme.next_code(&previous, date(2024, 1, 1).unwrap())?,
+        Code {
+            date: date(2024, 1, 1),
+            index: 3
+        }
+    );
+    assert_eq!(
+        scheme.next_code(&previous, date(2024, 1, 5).unwrap())?,
+        Code {
+            date: date(2024, 1, 5),
+            index: 1
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn date_index_strip_and_format() {
+    let scheme = DateIndexScheme::default();
+    let code = Code {
+        date: date(2024, 1, 1),
+        index: 3,
+    };
+
+    assert_eq!(scheme.strip_prefix("01__test.sql"), "test.sql");
+    assert_eq!(scheme.strip_prefix("test.sql"), "test.sql");
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &code, "test.sql")
+            .unwrap(),
+        "V20240101.03__test.sql"
+    );
+}
+
+#[test]
+fn date_index_overflow() -> anyhow::Result<()> {
+    let today = date(2024, 1, 1).unwrap();
+    let last = Code {
+        date: date(2024, 1, 1),
+        index: 99,
+    };
+
+    let widen = DateIndexScheme::new(2, OverflowPolicy::Widen, "__");
+    let code = widen.next_code(&last, today)?;
+    assert_eq!(
+        widen
+            .format_name(ScriptKind::Versioned, &code, "test.sql")
+            .unwrap(),
+        "V20240101.100__test.sql"
+    );
+    assert_eq!(widen.parse("V20240101.100__test.sql")?.unwrap().code, code);
+
+    let next_day = DateIndexScheme::new(2, OverflowPolicy::NextDay, "__");
+    let code = next_day.next_code(&last, today)?;
+    assert_eq!(
+        next_day
+            .format_name(ScriptKind::Versioned, &code, "test.sql")
+            .unwrap(),
+        "V20240102.01__test.sql"
+    );
+    let code = next_day.next_code(&code, today)?;
+    assert_eq!(
+        next_day
+            .format_name(ScriptKind::Versioned, &code, "test.sql")
+            .unwrap(),
+        "V20240102.02__test.sql"
+    );
+
+    let error = DateIndexScheme::new(2, OverflowPolicy::Error, "__");
+    assert!(error.next_code(&last, today).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn date_index_width() -> anyhow::Result<()> {
+    let scheme = DateIndexScheme::new(3, OverflowPolicy::Error, "__");
+    let last = Code {
+        date: date(2024, 1, 1),
+        index: 99,
+    };
+
+    let code = scheme.next_code(&last, date(2024, 1, 1).unwrap())?;
+
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &code, "test.sql")
+            .unwrap(),
+        "V20240101.100__test.sql"
+    );
+    assert_eq!(
+        scheme
+            .format_name(
+                ScriptKind::Versioned,
+                &Code {
+                    date: date(2024, 1, 1),
+                    index: 7
+                },
+                "test.sql"
+            )
+            .unwrap(),
+        "V20240101.007__test.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn flyway_prefixes() -> anyhow::Result<()> {
+    let scheme = DateIndexScheme::default();
+
+    let undo = scheme.parse("U20240101.02__file1.sql")?.unwrap();
+    assert_eq!(undo.kind, ScriptKind::Undo);
+    assert_eq!(undo.code.index, 2);
+    let baseline = scheme.parse("B20240101.01__base.sql")?.unwrap();
+    assert_eq!(baseline.kind, ScriptKind::Baseline);
+    assert_eq!(scheme.parse("R__view.sql")?, None);
+    assert_eq!(scheme.parse("beforeMigrate.sql")?, None);
+
+    assert_eq!(
+        scheme.classify("R__view.sql"),
+        (ScriptKind::Repeatable, "view.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("beforeMigrate.sql"),
+        (ScriptKind::Callback, "beforeMigrate.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("afterMigrate__log.sql"),
+        (ScriptKind::Callback, "afterMigrate__log.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("U01__file1.sql"),
+        (ScriptKind::Undo, "01__file1.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("U__file1.sql"),
+        (ScriptKind::Undo, "file1.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("Update_table.sql"),
+        (ScriptKind::Versioned, "Update_table.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("afterthought.sql"),
+        (ScriptKind::Versioned, "afterthought.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("afterFixingTheBug.sql"),
+        (ScriptKind::Versioned, "afterFixingTheBug.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("afterEachMigrateError.sql"),
+        (ScriptKind::Callback, "afterEachMigrateError.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("B__baseline.sql"),
+        (ScriptKind::Baseline, "baseline.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("B01__baseline.sql"),
+        (ScriptKind::Baseline, "01__baseline.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("Backfill_users.sql"),
+        (ScriptKind::Versioned, "Backfill_users.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("B2B_orders.sql"),
+        (ScriptKind::Versioned, "B2B_orders.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("U1_update.sql"),
+        (ScriptKind::Versioned, "U1_update.sql".into())
+    );
+    assert_eq!(
+        scheme.classify("U1.2__file1.sql"),
+        (ScriptKind::Undo, "1.2__file1.sql".into())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn flyway_separator() -> anyhow::Result<()> {
+    let scheme = DateIndexScheme::new(2, OverflowPolicy::Error, "-");
+    let code = Code {
+        date: date(2024, 1, 1),
+        index: 3,
+    };
+
+    assert_eq!(
+        scheme.parse("V20240101.02-file1.sql")?.unwrap().description,
+        "file1.sql"
+    );
+    assert_eq!(scheme.parse("V20240101.02__file1.sql")?, None);
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Undo, &code, "file1.sql")
+            .unwrap(),
+        "U20240101.03-file1.sql"
+    );
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Repeatable, &code, "view.sql")
+            .unwrap(),
+        "R-view.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn timestamp_parse() {
+    let scheme = TimestampScheme::default();
+
+    assert_eq!(
+        scheme.parse("20240101123005_create_users.sql").unwrap(),
+        Some(ParsedName {
+            kind: ScriptKind::Versioned,
+            code: Code {
+                date: date(2024, 1, 1),
+                index: 12 * 3600 + 30 * 60 + 5
+            },
+            description: "create_users.sql".to_string()
+        })
+    );
+    assert_eq!(scheme.parse("V20240101.01__file1.sql").unwrap(), None);
+    assert_eq!(
+        scheme
+            .parse("20241340000000_file1.sql")
+            .unwrap_err()
+            .exit_code(),
+        13
+    );
+}
+
+#[test]
+fn diesel_timestamps() -> anyhow::Result<()> {
+    let scheme = TimestampScheme::diesel("_");
+
+    let parsed = scheme.parse("2024-01-01-123005_create_users")?.unwrap();
+    assert_eq!(
+        parsed.code,
+        Code {
+            date: date(2024, 1, 1),
+            index: 12 * 3600 + 30 * 60 + 5
+        }
+    );
+    assert_eq!(parsed.description, "create_users");
+    assert_eq!(scheme.parse("20240101123005_create_users")?, None);
+
+    let next = scheme.next_code(&parsed.code, date(2024, 1, 1).unwrap())?;
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &next, "add_email")
+            .unwrap(),
+        "2024-01-01-123006_add_email"
+    );
+
+    // Sources made with `diesel migration generate` lose their own timestamp
+    assert_eq!(
+        scheme.strip_prefix("2024-05-01-093000_add_email"),
+        "add_email"
+    );
+    assert_eq!(scheme.strip_prefix("01_add_email"), "add_email");
+
+    Ok(())
+}
+
+#[test]
+fn invalid_codes_are_not_formatted() {
+    let without_date = Code {
+        date: None,
+        index: 1,
+    };
+    let past_midnight = Code {
+        date: date(2024, 1, 1),
+        index: 24 * 3600,
+    };
+
+    let timestamp = TimestampScheme::default();
+    assert_eq!(
+        timestamp
+            .format_name(ScriptKind::Versioned, &past_midnight, "a.sql")
+            .unwrap_err()
+            .to_string(),
+        format!("Code with an invalid time: {:?}", past_midnight)
+    );
+    assert!(timestamp
+        .format_name(ScriptKind::Undo, &without_date, "a.sql")
+        .is_err());
+    assert_eq!(
+        timestamp
+            .format_name(ScriptKind::Repeatable, &without_date, "view.sql")
+            .unwrap(),
+        "view.sql"
+    );
+
+    assert!(DateIndexScheme::default()
+        .format_name(ScriptKind::Versioned, &without_date, "a.sql")
+        .is_err());
+}
+
+#[test]
+fn timestamp_next_code() -> anyhow::Result<()> {
+    let scheme = TimestampScheme::default();
+    let last = Code {
+        date: date(2024, 1, 1),
+        index: 23 * 3600 + 59 * 60 + 59,
+    };
+
+    // One second after the newest target, across midnight
+    let next = scheme.next_code(&last, date(2024, 1, 1).unwrap())?;
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &next, "a.sql")
+            .unwrap(),
+        "20240102000000_a.sql"
+    );
+    let next = scheme.next_code(&next, date(2024, 1, 1).unwrap())?;
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &next, "b.sql")
+            .unwrap(),
+        "20240102000001_b.sql"
+    );
+
+    // A later day starts at midnight
+    let next = scheme.next_code(&next, date(2024, 3, 1).unwrap())?;
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &next, "c.sql")
+            .unwrap(),
+        "20240301000000_c.sql"
+    );
+
+    assert_eq!(scheme.strip_prefix("01_create.sql"), "create.sql");
+    assert_eq!(
+        scheme.strip_prefix("20231231000000_create.sql"),
+        "create.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sequence_parse_and_format() -> anyhow::Result<()> {
+    let scheme = SequenceScheme::default();
+
+    let parsed = scheme.parse("V0042__create_users.sql")?.unwrap();
+    assert_eq!(
+        parsed.code,
+        Code {
+            date: None,
+            index: 42
+        }
+    );
+    assert_eq!(parsed.description, "create_users.sql");
+    assert_eq!(scheme.parse("V20240101.01__file1.sql")?, None);
+
+    let next = scheme.next_code(&parsed.code, date(2024, 1, 1).unwrap())?;
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &next, "a.sql")
+            .unwrap(),
+        "V0043__a.sql"
+    );
+    assert_eq!(
+        scheme
+            .format_name(
+                ScriptKind::Versioned,
+                &Code {
+                    date: None,
+                    index: 12345
+                },
+                "a.sql"
+            )
+            .unwrap(),
+        "V12345__a.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sequence_custom_prefix() -> anyhow::Result<()> {
+    let scheme = SequenceScheme::new("", 3, "_");
+
+    assert_eq!(scheme.parse("007_seed.sql")?.unwrap().code.index, 7);
+    assert_eq!(
+        scheme
+            .format_name(
+                ScriptKind::Versioned,
+                &Code {
+                    date: None,
+                    index: 8
+                },
+                "a.sql"
+            )
+            .unwrap(),
+        "008_a.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pattern_parse() -> anyhow::Result<()> {
+    let scheme = PatternScheme::new(
+        r"^v(?<date>\d{4}_\d\d_\d\d)_(?<index>\d+)-(?<name>.+)$",
+        Scheme::DateIndex,
+        Box::new(DateIndexScheme::default()),
+    )?;
+
+    assert_eq!(
+        scheme.parse("v2024_01_01_03-name.sql")?,
+        Some(ParsedName {
+            kind: ScriptKind::Versioned,
+            code: Code {
+                date: date(2024, 1, 1),
+                index: 3
+            },
+            description: "name.sql".to_string()
+        })
+    );
+    assert_eq!(
+        scheme.parse("V20240101.03__name.sql")?.unwrap().code,
+        Code {
+            date: date(2024, 1, 1),
+            index: 3
+        }
+    );
+    assert_eq!(scheme.parse("README.md")?, None);
+    assert_eq!(
+        scheme
+            .parse("v2024_13_01_03-name.sql")
+            .unwrap_err()
+            .exit_code(),
+        13
+    );
+
+    // New names still follow the inner scheme
+    let code = scheme.next_code(
+        &scheme.parse("v2024_01_01_03-name.sql")?.unwrap().code,
+        date(2024, 1, 1).unwrap(),
+    )?;
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &code, "next.sql")
+            .unwrap(),
+        "V20240101.04__next.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pattern_version() -> anyhow::Result<()> {
+    let scheme = PatternScheme::new(
+        r"^(?<version>\d+)-(?<name>.+)$",
+        Scheme::Sequence,
+        Box::new(SequenceScheme::default()),
+    )?;
+
+    assert_eq!(
+        scheme.parse("0012-name.sql")?.unwrap().code,
+        Code {
+            date: None,
+            index: 12
+        }
+    );
+    assert_eq!(
+        scheme.parse("1.2.3-name.sql")?,
+        None,
+        "dotted versions aren't supported"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pattern_time() -> anyhow::Result<()> {
+    let scheme = PatternScheme::new(
+        r"^(?<date>\d{4}-\d\d-\d\d)-(?<time>\d{6})_(?<name>.+)$",
+        Scheme::Timestamp,
+        Box::new(TimestampScheme::default()),
+    )?;
+
+    let code = scheme.parse("2024-01-01-123005_name.sql")?.unwrap().code;
+    assert_eq!(
+        code,
+        Code {
+            date: date(2024, 1, 1),
+            index: 12 * 3600 + 30 * 60 + 5
+        }
+    );
+    assert_eq!(
+        scheme
+            .format_name(ScriptKind::Versioned, &code, "name.sql")
+            .unwrap(),
+        "20240101123005_name.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pattern_validation() {
+    let check = |pattern: &str, scheme: Scheme| {
+        let inner: Box<dyn NamingScheme> = match scheme {
+            Scheme::DateIndex => Box::new(DateIndexScheme::default()),
+            Scheme::Timestamp => Box::new(TimestampScheme::default()),
+            Scheme::Diesel => Box::new(TimestampScheme::diesel("_")),
+            Scheme::Sequence => Box::new(SequenceScheme::default()),
+        };
+        PatternScheme::new(pattern, scheme, inner)
+            .err()
+            .unwrap()
+            .to_string()
+    };
+
+    assert!(check(r"^(?<index>\d+)-.+$", Scheme::DateIndex)
+        .starts_with("The target pattern needs a (?<name>...) group"));
+    assert!(check(r"^(?<date>\d{8})-(?<name>.+)$", Scheme::DateIndex)
+        .starts_with("The target pattern needs a (?<index>...) group for the date-index scheme"));
+    assert!(check(r"^(?<index>\d+)-(?<name>.+)$", Scheme::DateIndex)
+        .starts_with("The target pattern needs a (?<date>...) group for the date-index scheme"));
+    assert!(check(r"^(?<version>\d+)-(?<name>.+)$", Scheme::DateIndex)
+        .starts_with("The target pattern needs a (?<date>...) group for the date-index scheme"));
+    assert!(check(
+        r"^(?<date>\d{8})(?<index>\d{6})_(?<name>.+)$",
+        Scheme::Timestamp
+    )
+    .starts_with("The target pattern needs a (?<time>...) group for the timestamp scheme"));
+    assert!(check(r"^(?<date>\d{8})-(?<name>.+)$", Scheme::Sequence).starts_with(
+        "The target pattern needs an (?<index>...) or a (?<version>...) group for the sequence scheme"
+    ));
+    assert!(check(
+        r"^(?<date>\d{8})-(?<index>\d+)-(?<name>.+)$",
+        Scheme::Sequence
+    )
+    .starts_with("The target pattern can't have a (?<date>...) group with the sequence scheme"));
+    assert!(check(r"^(?<index>\d+", Scheme::DateIndex).starts_with("Invalid target pattern"));
+}
+
+#[test]
+fn sanitized_strip_patterns() -> anyhow::Result<()> {
+    let scheme = SanitizedScheme::new(
+        &[r"^[A-Z]+-\d+_".to_string(), r"^\d+[_-]+".to_string()],
+        false,
+        None,
+        Box::new(DateIndexScheme::default()),
+    )?;
+
+    assert_eq!(
+        scheme.strip_prefix("TICKET-123_01__add_column.sql"),
+        "add_column.sql"
+    );
+    assert_eq!(scheme.strip_prefix("1_add_column.sql"), "add_column.sql");
+    assert_eq!(scheme.strip_prefix("001-add_column.sql"), "add_column.sql");
+    assert_eq!(scheme.strip_prefix("Add Column.sql"), "Add Column.sql");
+
+    // Patterns without an anchor still only remove a prefix
+    let scheme = SanitizedScheme::new(
+        &[r"\d+-".to_string()],
+        false,
+        None,
+        Box::new(DateIndexScheme::default()),
+    )?;
+
+    assert_eq!(scheme.strip_prefix("12-add_col.sql"), "add_col.sql");
+    assert_eq!(
+        scheme.strip_prefix("add_col-2-users.sql"),
+        "add_col-2-users.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sanitized_normalize() -> anyhow::Result<()> {
+    let scheme = SanitizedScheme::new(&[], true, Some(16), Box::new(DateIndexScheme::default()))?;
+
+    assert_eq!(scheme.strip_prefix("01__Add Column.SQL"), "add_column.SQL");
+    assert_eq!(
+        scheme.strip_prefix("Přidání sloupců.sql"),
+        "pridani_sloupcu.sql"
+    );
+    assert_eq!(
+        scheme.strip_prefix("Straße → Ørsted.sql"),
+        "strasse___orsted.sql"
+    );
+    assert_eq!(
+        scheme.strip_prefix("a very long description of the change.sql"),
+        "a_very_long_desc.sql"
+    );
+    assert_eq!(
+        scheme.strip_prefix("rename some_column.sql"),
+        "rename_some_colu.sql"
+    );
+    assert_eq!(
+        scheme.strip_prefix("12345678901234_ x.sql"),
+        "12345678901234.sql"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sanitized_invalid_pattern() {
+    let err = SanitizedScheme::new(
+        &["(".to_string()],
+        false,
+        None,
+        Box::new(DateIndexScheme::default()),
+    )
+    .err()
+    .unwrap();
+
+    assert!(err.to_string().starts_with("Invalid strip pattern \"(\""));
+}
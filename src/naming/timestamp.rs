@@ -0,0 +1,118 @@
+use std::borrow::Cow;
+
+use anyhow::Context;
+use chrono::{NaiveDateTime, NaiveTime, Timelike};
+use regex::Regex;
+
+use super::{Code, NamingScheme, ParsedName, ScriptKind};
+use crate::error::Error;
+
+/// The `{YYYYMMDDHHMMSS}_{name}` scheme used by Rails and goose, or Diesel's
+/// `{YYYY-MM-DD-HHMMSS}_{name}` built with [`TimestampScheme::diesel`].
+///
+/// The code keeps the day in `date` and the seconds since midnight in `index`.
+/// New timestamps start at midnight of the day and are one second apart.
+pub struct TimestampScheme {
+    pattern: Regex,
+    source_prefix: Regex,
+    separator: String,
+    format: &'static str,
+}
+
+impl TimestampScheme {
+    pub fn new(separator: &str) -> Self {
+        Self::with_format(r#"\d{14}"#, "%Y%m%d%H%M%S", separator)
+    }
+
+    /// Diesel's dashed timestamps, e.g. `2024-01-01-000000_name`.
+    pub fn diesel(separator: &str) -> Self {
+        Self::with_format(r#"\d{4}-\d\d-\d\d-\d{6}"#, "%Y-%m-%d-%H%M%S", separator)
+    }
+
+    fn with_format(timestamp: &str, format: &'static str, separator: &str) -> Self {
+        Self {
+            pattern: Regex::new(&format!(
+                r#"^({}){}(.+)$"#,
+                timestamp,
+                regex::escape(separator)
+            ))
+            .unwrap(),
+            // Sources may carry a timestamp of either format already
+            source_prefix: Regex::new(r#"^(\d{4}-\d\d-\d\d-\d{6}|\d+)_+"#).unwrap(),
+            separator: separator.to_string(),
+            format,
+        }
+    }
+}
+
+impl Default for TimestampScheme {
+    fn default() -> Self {
+        Self::new("_")
+    }
+}
+
+fn timestamp(code: &Code) -> anyhow::Result<NaiveDateTime> {
+    let date = code
+        .date
+        .with_context(|| format!("Code without a date: {:?}", code))?;
+    let time = NaiveTime::from_num_seconds_from_midnight_opt(code.index as u32, 0)
+        .with_context(|| format!("Code with an invalid time: {:?}", code))?;
+
+    Ok(date.and_time(time))
+}
+
+impl NamingScheme for TimestampScheme {
+    fn parse(&self, file_name: &str) -> Result<Option<ParsedName>, Error> {
+        let Some(caps) = self.pattern.captures(file_name) else {
+            return Ok(None);
+        };
+
+        let timestamp = NaiveDateTime::parse_from_str(&caps[1], self.format).map_err(|err| {
+            Error::UnparsableTargetName {
+                name: file_name.to_string(),
+                reason: format!("invalid timestamp {}: {}", &caps[1], err),
+            }
+        })?;
+
+        Ok(Some(ParsedName {
+            kind: ScriptKind::Versioned,
+            code: Code {
+                date: Some(timestamp.date()),
+                index: timestamp.num_seconds_from_midnight() as u64,
+            },
+            description: caps[2].to_string(),
+        }))
+    }
+
+    fn next_code(&self, previous: &Code, today: chrono::NaiveDate) -> anyhow::Result<Code> {
+        let next = (timestamp(previous)? + chrono::Duration::seconds(1))
+            .max(today.and_time(NaiveTime::MIN));
+
+        Ok(Code {
+            date: Some(next.date()),
+            index: next.num_seconds_from_midnight() as u64,
+        })
+    }
+
+    fn strip_prefix<'a>(&self, file_name: &'a str) -> Cow<'a, str> {
+        self.source_prefix.replace(file_name, "")
+    }
+
+    fn format_name(
+        &self,
+        kind: ScriptKind,
+        code: &Code,
+        description: &str,
+    ) -> anyhow::Result<String> {
+        if matches!(kind, ScriptKind::Repeatable | ScriptKind::Callback) {
+            return Ok(description.to_string());
+        }
+
+        Ok(format!(
+            "{}{}{}",
+            timestamp(code)?.format(self.format),
+            self.separator,
+            description
+        ))
+    }
+}
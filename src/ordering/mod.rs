@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+#[cfg(test)]
+mod tests;
+
+/// Order in which the source scripts get their codes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceOrder {
+    /// By file name, numeric prefixes by their value
+    #[default]
+    Name,
+    /// By the first commit on the source branch which contains the script
+    Commit,
+    /// As listed in the manifest file of the source folder
+    Manifest,
+}
+
+/// Compares source paths by their file name with [`natural_cmp`], then by the whole path.
+pub fn name_cmp(a: &str, b: &str) -> Ordering {
+    let file_name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+
+    natural_cmp(&file_name(a), &file_name(b)).then_with(|| natural_cmp(a, b))
+}
+
+/// Orders `files` as listed in `manifest`, one path relative to `folder` per line.
+///
+/// Blank lines and `#` comments are skipped, listed paths which aren't among the files
+/// are ignored. Every file has to be listed.
+pub fn by_manifest<T>(
+    mut files: Vec<(String, T)>,
+    folder: &Path,
+    manifest: &str,
+) -> anyhow::Result<Vec<(String, T)>> {
+    let mut ordered = Vec::with_capacity(files.len());
+
+    for line in manifest.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(position) = files.iter().position(|(path, _)| {
+            Path::new(path).strip_prefix(folder).ok() == Some(Path::new(line))
+        }) {
+            ordered.push(files.remove(position));
+        }
+    }
+
+    if !files.is_empty() {
+        let missing: Vec<String> = files.into_iter().map(|(path, _)| path).collect();
+        anyhow::bail!("Source scripts missing from the manifest: {:?}", missing);
+    }
+
+    Ok(ordered)
+}
+
+/// Compares two strings so that runs of digits are ordered by their numeric value,
+/// e.g. `9` < `10` and `1.2` < `1.10`. Everything else is compared bytewise.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = chunks(a);
+    let mut b = chunks(b);
+
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(left), Some(right)) => {
+                let ordering = compare_chunks(left, right);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+fn compare_chunks(left: &str, right: &str) -> Ordering {
+    let is_number = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+
+    if is_number(left) && is_number(right) {
+        let left_trimmed = left.trim_start_matches('0');
+        let right_trimmed = right.trim_start_matches('0');
+
+        left_trimmed
+            .len()
+            .cmp(&right_trimmed.len())
+            .then_with(|| left_trimmed.cmp(right_trimmed))
+            .then_with(|| left.len().cmp(&right.len()))
+    } else {
+        left.cmp(right)
+    }
+}
+
+/// Splits the string into alternating runs of digits and non-digits.
+fn chunks(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
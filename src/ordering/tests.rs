@@ -0,0 +1,70 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use super::{by_manifest, name_cmp, natural_cmp};
+
+#[test]
+fn numbers_by_value() {
+    assert_eq!(natural_cmp("9", "10"), Ordering::Less);
+    assert_eq!(natural_cmp("10", "9"), Ordering::Greater);
+    assert_eq!(natural_cmp("10", "10"), Ordering::Equal);
+}
+
+#[test]
+fn mixed_segments() {
+    assert_eq!(natural_cmp("1.2", "1.10"), Ordering::Less);
+    assert_eq!(natural_cmp("v2/a", "v10/a"), Ordering::Less);
+    assert_eq!(natural_cmp("a", "b"), Ordering::Less);
+    assert_eq!(natural_cmp("1", "1/a"), Ordering::Less);
+}
+
+#[test]
+fn leading_zeros() {
+    assert_eq!(natural_cmp("02", "10"), Ordering::Less);
+    assert_eq!(natural_cmp("2", "02"), Ordering::Less);
+}
+
+#[test]
+fn names_before_folders() {
+    let mut files = vec![
+        "db/new/10__x.sql",
+        "db/new/2__y.sql",
+        "db/a/2__y.sql",
+        "db/new/1__z.sql",
+    ];
+    files.sort_by(|a, b| name_cmp(a, b));
+
+    assert_eq!(
+        files,
+        vec![
+            "db/new/1__z.sql",
+            "db/a/2__y.sql",
+            "db/new/2__y.sql",
+            "db/new/10__x.sql"
+        ]
+    );
+}
+
+#[test]
+fn manifest_order() -> anyhow::Result<()> {
+    let files = vec![
+        ("db/new/a.sql".to_string(), 1),
+        ("db/new/b.sql".to_string(), 2),
+        ("db/new/sub/c.sql".to_string(), 3),
+    ];
+    let manifest = "# applied top to bottom\nsub/c.sql\n\n  a.sql\nmerged.sql\nb.sql\n";
+
+    let ordered = by_manifest(files.clone(), Path::new("db/new"), manifest)?;
+    assert_eq!(
+        ordered.iter().map(|(_, id)| *id).collect::<Vec<_>>(),
+        vec![3, 1, 2]
+    );
+
+    let err = by_manifest(files, Path::new("db/new"), "a.sql").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Source scripts missing from the manifest: [\"db/new/b.sql\", \"db/new/sub/c.sql\"]"
+    );
+
+    Ok(())
+}
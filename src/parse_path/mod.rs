@@ -3,6 +3,7 @@ use std::path::{Component, Path, PathBuf};
 use anyhow::Context;
 
 use crate::config::PathConfig;
+use crate::error::Error;
 
 #[cfg(test)]
 mod tests;
@@ -20,12 +21,12 @@ pub fn parse_path(file_path: &str, root: &str) -> anyhow::Result<PathConfig> {
     let file_path = normalize_path(file_path);
 
     // Ensure the file path contains the target folder as a prefix
-    let stripped_path = file_path.strip_prefix(&root).with_context(|| {
-        format!(
-            "Can't find the target folder prefix '{:?}' in: {:?}",
-            &root, file_path
-        )
-    })?;
+    let stripped_path = file_path
+        .strip_prefix(&root)
+        .map_err(|_| Error::PrefixMismatch {
+            root: root.clone(),
+            path: file_path.clone(),
+        })?;
 
     // Extract file name
     let file_name = stripped_path
@@ -0,0 +1,45 @@
+use anyhow::Context;
+
+use std::path::Path;
+
+use crate::error::Error;
+use crate::git::resolve_commit;
+use crate::process::Plan;
+
+#[cfg(test)]
+mod tests;
+
+/// Writes the plan as JSON so it can be reviewed and applied later.
+pub fn save(plan: &Plan, path: &Path) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(plan)?;
+
+    std::fs::write(path, json).with_context(|| format!("Can't write the plan: {:?}", path))
+}
+
+pub fn load(path: &Path) -> anyhow::Result<Plan> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Can't read the plan: {:?}", path))?;
+
+    serde_json::from_str(&json).with_context(|| format!("Invalid plan: {:?}", path))
+}
+
+/// Fails unless both branches of the plan still point at the commits it was made for.
+pub fn verify(repo_path: &Path, plan: &Plan) -> anyhow::Result<()> {
+    for (branch, planned) in [
+        (&plan.source_branch, &plan.source_commit),
+        (&plan.target_branch, &plan.target_commit),
+    ] {
+        let current = resolve_commit(repo_path, branch)?.to_string();
+
+        if current != *planned {
+            return Err(Error::StalePlan {
+                branch: branch.clone(),
+                planned: planned.clone(),
+                current,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
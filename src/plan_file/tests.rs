@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use crate::error::find_error;
+use crate::git::tests::internal::{commit_files, create_test_repository};
+use crate::process::{process, Configuration};
+
+use super::{load, save, verify};
+
+fn configuration(repo_path: PathBuf) -> Configuration {
+    Configuration {
+        repo_path,
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn plan_round_trip() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let plan = process(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    let path = temp_dir.path().join("plan.json");
+    save(&plan, &path).unwrap();
+    let loaded = load(&path).unwrap();
+
+    assert_eq!(loaded.source_commit, plan.source_commit);
+    assert_eq!(loaded.target_commit, plan.target_commit);
+    assert_eq!(
+        loaded.anchor.as_ref().unwrap().path,
+        "db/migrate/1/V20240101.01__file1.txt"
+    );
+    assert_eq!(loaded.renames, plan.renames);
+    verify(temp_dir.path(), &loaded).unwrap();
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn verify_refuses_moved_branch() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let plan = process(
+        configuration(temp_dir.path().to_path_buf()),
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+    )
+    .unwrap();
+
+    commit_files(
+        &repo,
+        &["db/migrate/new/02__late.txt"],
+        "late",
+        1_700_000_000,
+    );
+    let current = repo.revparse_single("develop").unwrap().id().to_string();
+
+    let err = verify(temp_dir.path(), &plan).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Branch develop moved from {} to {} since the plan was made",
+            plan.source_commit, current
+        )
+    );
+    assert_eq!(find_error(&err).unwrap().exit_code(), 16);
+
+    temp_dir.close().unwrap();
+}
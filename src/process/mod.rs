@@ -1,8 +1,20 @@
-use crate::git::find_files_in_branch;
+use crate::companions::{group_stem, Companions};
+use crate::config::PathConfig;
+use crate::error::Error;
+use crate::git::{
+    find_blobs_in_branch, find_new_files_in_branch, find_tree_in_branch, first_commit_times,
+    read_file_in_branch, resolve_commit,
+};
+use crate::naming::{Code, DateIndexScheme, NamingScheme, ParsedName, ScriptKind};
+use crate::ordering::{by_manifest, name_cmp, natural_cmp, SourceOrder};
 use crate::{format_target_name::format_target_name, parse_path};
 
 use anyhow::Context;
-use regex::Regex;
+use clap::ValueEnum;
+use gix::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 #[cfg(test)]
@@ -16,6 +28,28 @@ pub struct Configuration {
     pub extension_filter: Option<String>,
     pub target_directory_filter: Option<PathBuf>,
     pub source_directory_filter: Option<PathBuf>,
+    pub naming_scheme: Box<dyn NamingScheme>,
+    /// Suffixes of files renamed together under one code
+    pub companions: Vec<Companions>,
+    pub layout: Layout,
+    pub source_order: SourceOrder,
+    /// File name of the manifest in the source folder, for [`SourceOrder::Manifest`]
+    pub manifest: String,
+}
+
+/// File name of the manifest listing the source scripts in order.
+pub const DEFAULT_MANIFEST: &str = ".fmr-order";
+
+/// What a single migration is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// Every file is a migration
+    #[default]
+    Files,
+    /// Every directory holding files is a migration, e.g. Diesel's `2024-01-01-000000_name/`
+    /// together with the diesel scheme
+    Directories,
 }
 
 impl Default for Configuration {
@@ -28,80 +62,570 @@ impl Default for Configuration {
             extension_filter: None,
             target_directory_filter: None,
             source_directory_filter: None,
+            naming_scheme: Box::new(DateIndexScheme::default()),
+            companions: vec![],
+            layout: Layout::default(),
+            source_order: SourceOrder::default(),
+            manifest: DEFAULT_MANIFEST.to_string(),
         }
     }
 }
 
-pub fn process(
-    config: Configuration,
-    today: chrono::NaiveDate,
-) -> anyhow::Result<Vec<(String, String)>> {
-    let target_files = find_files_in_branch(
+/// Result of the analysis, every rename that has to happen to merge the source branch.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Plan {
+    /// Source branch as it was passed
+    pub source_branch: String,
+    /// Target branch as it was passed
+    pub target_branch: String,
+    /// Commit the source branch resolved to
+    pub source_commit: String,
+    /// Commit the target branch resolved to
+    pub target_commit: String,
+    /// Newest target script the new codes follow
+    pub anchor: Option<Anchor>,
+    pub renames: Vec<Rename>,
+    /// Source scripts which are already in the target
+    pub merged: Vec<Merged>,
+    /// Released target scripts which were changed on the source branch
+    pub edited: Vec<Edited>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Anchor {
+    pub path: String,
+    pub code: Code,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rename {
+    pub source: String,
+    pub target: String,
+    /// Blob id of the source file, tree id in the directory layout
+    pub blob: String,
+    pub kind: ScriptKind,
+    /// Code the script got, `None` for scripts which are moved as they are
+    pub code: Option<Code>,
+}
+
+/// Source script found in the target, with the same content or description.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Merged {
+    pub source: String,
+    /// Target script it was merged as
+    pub target: String,
+}
+
+/// Versioned target script whose content differs on the source branch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edited {
+    pub path: String,
+    /// Blob id in the target branch
+    pub target_blob: String,
+    /// Blob id in the source branch
+    pub source_blob: String,
+}
+
+pub fn process(config: Configuration, today: chrono::NaiveDate) -> anyhow::Result<Plan> {
+    let target_commit = resolve_commit(config.repo_path.as_path(), &config.target_branch)
+        .with_context(|| format!("Can't open target branch: {0}", &config.target_branch))?
+        .to_string();
+    let source_commit = resolve_commit(config.repo_path.as_path(), &config.source_branch)
+        .with_context(|| format!("Can't open source branch: {0}", &config.source_branch))?
+        .to_string();
+
+    eprintln!("Target {} is at {}", &config.target_branch, target_commit);
+    eprintln!("Source {} is at {}", &config.source_branch, source_commit);
+
+    let target_file_blobs = find_blobs_in_branch(
         config.repo_path.as_path(),
-        &config.target_branch,
+        &target_commit,
         config.extension_filter.as_deref(),
         config.target_directory_filter.as_deref(),
     )
     .with_context(|| format!("Can't open target branch: {0}", &config.target_branch))?;
+    let target_blobs = match config.layout {
+        Layout::Files => target_file_blobs.clone(),
+        Layout::Directories => {
+            let files: Vec<String> = target_file_blobs
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect();
+            with_tree_ids(&config, &target_commit, migration_directories(&files, None))?
+        }
+    };
+    let target_files: Vec<String> = target_blobs.iter().map(|(path, _)| path.clone()).collect();
 
-    let mut paths: Vec<(String, String)> = vec![];
-
-    if let Some(last_target_file) = target_files.last() {
-        println!("Last script: {:?}", last_target_file);
-        let source = parse_path(last_target_file, &config.root_directory)?;
+    let mut plan = Plan {
+        source_branch: config.source_branch.clone(),
+        target_branch: config.target_branch.clone(),
+        source_commit,
+        target_commit,
+        ..Default::default()
+    };
 
-        println!(
+    if !target_files.is_empty() {
+        eprintln!(
             "Looking for new source files in {:?}",
             &config.source_branch,
         );
 
-        let source_files = find_files_in_branch(
+        plan.edited = find_edited(&config, &plan, &target_file_blobs)?;
+        for edited in &plan.edited {
+            eprintln!(
+                "Released script edited on the source branch: {:?}",
+                edited.path
+            );
+        }
+
+        let source_files = find_new_files_in_branch(
             config.repo_path.as_path(),
-            &config.source_branch,
+            &plan.source_commit,
+            &plan.target_commit,
             config.extension_filter.as_deref(),
             config.source_directory_filter.as_deref(),
         )
         .with_context(|| format!("Can't open source branch: {0}", &config.source_branch))?;
+        let source_files = match config.layout {
+            Layout::Files => source_files,
+            Layout::Directories => {
+                let files: Vec<String> = source_files.into_iter().map(|(path, _)| path).collect();
+                with_tree_ids(
+                    &config,
+                    &plan.source_commit,
+                    migration_directories(&files, config.source_directory_filter.as_deref()),
+                )?
+            }
+        };
+
+        let scheme = config.naming_scheme.as_ref();
+        let landed = landed_since_fork(&config, &plan, &target_blobs)?;
+        let (source_files, merged) = split_merged(scheme, source_files, &landed);
+        plan.merged = merged;
+
+        let source_files = order_sources(&config, &plan, source_files)?;
+
+        eprintln!("Found {:?} files in source branch", source_files.len());
 
-        println!("Found {:?} files in source branch", source_files.len());
-        let (date, index) = get_code(last_target_file, today)?;
+        let Versioned {
+            file: last_target_file,
+            path: source,
+            mut code,
+            ..
+        } = find_anchor(scheme, &target_files, &config.root_directory)?;
+        eprintln!("Last script: {:?}", last_target_file);
+
+        plan.anchor = Some(Anchor {
+            path: last_target_file.to_string(),
+            code: code.clone(),
+        });
+
+        let mut undo_scripts = vec![];
+        let mut descriptions = vec![];
+        // Codes given to companion groups, by folder and stem
+        let mut groups: HashMap<(&Path, &str), Code> = HashMap::new();
+
+        for (source_name, blob) in source_files.iter() {
+            eprintln!("Processing file: {:?}", source_name);
+            let source_path = Path::new(source_name);
+            let file_name = source_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            let (kind, name) = scheme.classify(file_name);
+
+            let rename_code = match kind {
+                ScriptKind::Undo => {
+                    let description = scheme.strip_prefix(&name).to_string();
+                    undo_scripts.push((source_name, blob, description));
+                    continue;
+                }
+                ScriptKind::Repeatable | ScriptKind::Callback => None,
+                ScriptKind::Versioned | ScriptKind::Baseline => {
+                    let group = group_stem(&config.companions, file_name)
+                        .map(|stem| (source_path.parent().unwrap_or(Path::new("")), stem));
+
+                    let script_code = match group.and_then(|group| groups.get(&group)) {
+                        Some(group_code) => group_code.clone(),
+                        None => {
+                            code = scheme.next_code(&code, today)?;
+                            if let Some(group) = group {
+                                groups.insert(group, code.clone());
+                            }
+                            code.clone()
+                        }
+                    };
+
+                    descriptions
+                        .push((scheme.strip_prefix(&name).to_string(), script_code.clone()));
+                    Some(script_code)
+                }
+            };
 
-        for (i, source_name) in source_files.iter().enumerate() {
-            println!("Processing file: {:?}", source_name);
-            let real_index = index + i as i64 + 1;
             let target_name = format_target_name(
+                scheme,
                 Path::new(source.folder.as_str()),
                 source.version.as_deref(),
-                format!("V{}.{:02}", date, real_index).as_str(),
+                rename_code.as_ref().unwrap_or(&code),
                 source_name,
-            );
+            )?;
+
+            plan.renames.push(Rename {
+                source: source_name.clone(),
+                target: target_name,
+                blob: blob.to_string(),
+                kind,
+                code: rename_code,
+            });
+        }
+
+        // Undo scripts share the code of the script they revert and go next to it, the
+        // script is either renamed in this run or already released in the target
+        let released = parse_scripts(scheme, &target_files, &config.root_directory)?;
+
+        for (source_name, blob, description) in undo_scripts {
+            let (undo_code, folder, version) = descriptions
+                .iter()
+                .find(|(other, _)| *other == description)
+                .map(|(_, code)| (code, &source.folder, &source.version))
+                .or_else(|| {
+                    released
+                        .versioned
+                        .iter()
+                        .filter(|script| {
+                            script.kind == ScriptKind::Versioned
+                                && script.description == description
+                        })
+                        .max_by(|a, b| a.cmp_order(b))
+                        .map(|script| (&script.code, &script.path.folder, &script.path.version))
+                })
+                .with_context(|| format!("Can't find the script reverted by: {:?}", source_name))?;
+
+            let target_name = format_target_name(
+                scheme,
+                Path::new(folder.as_str()),
+                version.as_deref(),
+                undo_code,
+                source_name,
+            )?;
 
-            paths.push((source_name.clone(), target_name.clone()));
+            plan.renames.push(Rename {
+                source: source_name.clone(),
+                target: target_name,
+                blob: blob.to_string(),
+                kind: ScriptKind::Undo,
+                code: Some(undo_code.clone()),
+            });
         }
     }
 
-    Ok(paths)
+    Ok(plan)
 }
 
-fn get_code(filename: &str, today: chrono::NaiveDate) -> anyhow::Result<(String, i64)> {
-    let some = Regex::new(r#"V(\d{8})\.(\d{2})__.+"#).unwrap();
+/// Finds the versioned target scripts the source branch changed since it forked off.
+///
+/// In the directory layout every file of a versioned migration directory counts.
+fn find_edited(
+    config: &Configuration,
+    plan: &Plan,
+    target_file_blobs: &[(String, ObjectId)],
+) -> anyhow::Result<Vec<Edited>> {
+    let scheme = config.naming_scheme.as_ref();
 
-    let caps = some
-        .captures(filename)
-        .with_context(|| format!("Can't extract the date and index from: {:?}", filename))?;
+    let changed = find_new_files_in_branch(
+        config.repo_path.as_path(),
+        &plan.source_commit,
+        &plan.target_commit,
+        config.extension_filter.as_deref(),
+        config.target_directory_filter.as_deref(),
+    )
+    .with_context(|| format!("Can't open source branch: {0}", &config.source_branch))?;
 
-    let date = caps.get(1).unwrap().as_str();
-    let index = caps.get(2).unwrap().as_str();
+    let mut edited = vec![];
 
-    let today_code = today.format("%Y%m%d").to_string();
+    for (path, target_blob) in target_file_blobs {
+        let Some((_, source_blob)) = changed.iter().find(|(other, _)| other == path) else {
+            continue;
+        };
+        if source_blob == target_blob {
+            continue;
+        }
 
-    let date_parsed = chrono::NaiveDate::parse_from_str(date, "%Y%m%d").unwrap();
+        let script = match config.layout {
+            Layout::Files => Path::new(path).file_name(),
+            Layout::Directories => Path::new(path)
+                .parent()
+                .and_then(|parent| parent.file_name()),
+        };
+        let versioned = script
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| matches!(scheme.parse(name), Ok(Some(_))));
 
-    let result = if today > date_parsed {
-        (today_code, 0)
-    } else {
-        (date.to_string(), index.parse::<i64>().unwrap())
+        if versioned {
+            edited.push(Edited {
+                path: path.clone(),
+                target_blob: target_blob.to_string(),
+                source_blob: source_blob.to_string(),
+            });
+        }
+    }
+
+    Ok(edited)
+}
+
+/// Pairs `directories` with their tree ids in `commit`.
+fn with_tree_ids(
+    config: &Configuration,
+    commit: &str,
+    directories: Vec<String>,
+) -> anyhow::Result<Vec<(String, ObjectId)>> {
+    directories
+        .into_iter()
+        .map(|path| {
+            let id = find_tree_in_branch(config.repo_path.as_path(), commit, &path)?;
+            Ok((path, id))
+        })
+        .collect()
+}
+
+/// Target scripts added since the source branch forked off, only those can be earlier
+/// merges of source scripts. Directories count if any of their files was added.
+fn landed_since_fork(
+    config: &Configuration,
+    plan: &Plan,
+    target_blobs: &[(String, ObjectId)],
+) -> anyhow::Result<Vec<(String, ObjectId)>> {
+    let added = find_new_files_in_branch(
+        config.repo_path.as_path(),
+        &plan.target_commit,
+        &plan.source_commit,
+        config.extension_filter.as_deref(),
+        config.target_directory_filter.as_deref(),
+    )
+    .with_context(|| format!("Can't open target branch: {0}", &config.target_branch))?;
+
+    Ok(target_blobs
+        .iter()
+        .filter(|(path, _)| {
+            added.iter().any(|(file, _)| {
+                file == path
+                    || file
+                        .strip_prefix(path.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+        })
+        .cloned()
+        .collect())
+}
+
+/// Splits off the source scripts which are already in the target, either with the same
+/// blob or as a script of the same kind and description.
+fn split_merged(
+    scheme: &dyn NamingScheme,
+    source_files: Vec<(String, ObjectId)>,
+    target_blobs: &[(String, ObjectId)],
+) -> (Vec<(String, ObjectId)>, Vec<Merged>) {
+    let file_name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+
+    let targets: Vec<(&str, &ObjectId, Option<ParsedName>)> = target_blobs
+        .iter()
+        .map(|(path, id)| {
+            let parsed = scheme.parse(&file_name(path)).ok().flatten();
+            (path.as_str(), id, parsed)
+        })
+        .collect();
+
+    let mut merged = vec![];
+    let mut remaining = vec![];
+
+    for (source, blob) in source_files {
+        let name = file_name(&source);
+        let (kind, name) = scheme.classify(&name);
+        let description = scheme.strip_prefix(&name);
+
+        let found = targets.iter().find(|(_, id, _)| **id == blob).or_else(|| {
+            targets.iter().find(|(_, _, parsed)| {
+                parsed
+                    .as_ref()
+                    .is_some_and(|parsed| parsed.kind == kind && parsed.description == description)
+            })
+        });
+
+        match found {
+            Some((target, _, _)) => merged.push(Merged {
+                source,
+                target: target.to_string(),
+            }),
+            None => remaining.push((source, blob)),
+        }
+    }
+
+    (remaining, merged)
+}
+
+/// Sorts the source scripts into the order they get their codes in.
+fn order_sources(
+    config: &Configuration,
+    plan: &Plan,
+    mut files: Vec<(String, ObjectId)>,
+) -> anyhow::Result<Vec<(String, ObjectId)>> {
+    match config.source_order {
+        SourceOrder::Name => {
+            files.sort_by(|(a, _), (b, _)| name_cmp(a, b));
+        }
+        SourceOrder::Commit => {
+            let paths: Vec<String> = files.iter().map(|(path, _)| path.clone()).collect();
+            let times = first_commit_times(
+                config.repo_path.as_path(),
+                &plan.source_commit,
+                &plan.target_commit,
+                &paths,
+            )?;
+
+            files.sort_by(|(a, _), (b, _)| {
+                times.get(a).cmp(&times.get(b)).then_with(|| name_cmp(a, b))
+            });
+        }
+        SourceOrder::Manifest => {
+            let folder = config
+                .source_directory_filter
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(&config.root_directory));
+            let manifest_path = folder.join(&config.manifest).to_string_lossy().to_string();
+
+            let manifest = read_file_in_branch(
+                config.repo_path.as_path(),
+                &plan.source_commit,
+                &manifest_path,
+            )?
+            .with_context(|| {
+                format!(
+                    "Can't find the manifest {:?} in the source branch",
+                    manifest_path
+                )
+            })?;
+
+            files.retain(|(path, _)| *path != manifest_path);
+            files = by_manifest(files, &folder, &String::from_utf8_lossy(&manifest))?;
+        }
+    }
+
+    Ok(files)
+}
+
+/// Directories holding `files` in the order they are first seen, the `container`
+/// the migration directories live in is not a migration itself.
+pub fn migration_directories(files: &[String], container: Option<&Path>) -> Vec<String> {
+    let mut directories: Vec<String> = vec![];
+
+    for file in files {
+        let Some(parent) = Path::new(file).parent() else {
+            continue;
+        };
+        if parent.as_os_str().is_empty() || Some(parent) == container {
+            continue;
+        }
+
+        let directory = parent.to_string_lossy().to_string();
+        if !directories.contains(&directory) {
+            directories.push(directory);
+        }
+    }
+
+    directories
+}
+
+/// Script which follows the naming scheme.
+pub struct Versioned<'a> {
+    pub file: &'a str,
+    pub path: PathConfig,
+    pub kind: ScriptKind,
+    pub code: Code,
+    pub description: String,
+}
+
+impl Versioned<'_> {
+    /// Orders scripts by (version folder, code).
+    pub fn cmp_order(&self, other: &Versioned) -> Ordering {
+        natural_cmp(
+            self.path.version.as_deref().unwrap_or(""),
+            other.path.version.as_deref().unwrap_or(""),
+        )
+        .then_with(|| self.code.cmp(&other.code))
+    }
+}
+
+/// Files split by whether they follow the naming scheme.
+pub struct Scripts<'a> {
+    pub versioned: Vec<Versioned<'a>>,
+    pub rejected: Vec<&'a str>,
+    /// First file which isn't under the root directory
+    pub outside_root: Option<anyhow::Error>,
+}
+
+pub fn parse_scripts<'a>(
+    scheme: &dyn NamingScheme,
+    files: &'a [String],
+    root: &str,
+) -> Result<Scripts<'a>, Error> {
+    let mut scripts = Scripts {
+        versioned: vec![],
+        rejected: vec![],
+        outside_root: None,
     };
 
-    Ok(result)
+    for file in files {
+        let path = match parse_path(file, root) {
+            Ok(path) => path,
+            Err(err) => {
+                scripts.outside_root.get_or_insert(err);
+                continue;
+            }
+        };
+
+        match scheme.parse(&path.file_name)? {
+            Some(parsed) => scripts.versioned.push(Versioned {
+                file: file.as_str(),
+                path,
+                kind: parsed.kind,
+                code: parsed.code,
+                description: parsed.description,
+            }),
+            None => scripts.rejected.push(file.as_str()),
+        }
+    }
+
+    Ok(scripts)
+}
+
+/// Picks the target script with the highest (version folder, code).
+///
+/// Files outside of `root` are ignored, files which don't follow the naming scheme
+/// are rejected and listed in the error if no anchor is left.
+pub fn find_anchor<'a>(
+    scheme: &dyn NamingScheme,
+    target_files: &'a [String],
+    root: &str,
+) -> anyhow::Result<Versioned<'a>> {
+    let scripts = parse_scripts(scheme, target_files, root)?;
+
+    // Nothing lives under the root, most likely a wrong root directory
+    if scripts.versioned.is_empty() && scripts.rejected.is_empty() {
+        if let Some(err) = scripts.outside_root {
+            return Err(err);
+        }
+    }
+
+    let rejected = scripts.rejected;
+
+    scripts
+        .versioned
+        .into_iter()
+        .max_by(|a, b| a.cmp_order(b))
+        .ok_or_else(|| {
+            Error::NoAnchorScript {
+                rejected: rejected.into_iter().map(String::from).collect(),
+            }
+            .into()
+        })
 }
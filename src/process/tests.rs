@@ -1,8 +1,11 @@
 use std::path::PathBuf;
 
-use crate::git::tests::internal::create_test_repository;
+use crate::error::find_error;
+use crate::git::tests::internal::{commit_files, create_test_repository};
+use crate::naming::{DateIndexScheme, PatternScheme, Scheme, ScriptKind, TimestampScheme};
 
-use super::{process, Configuration};
+use super::{process, Configuration, Layout, Merged};
+use crate::ordering::SourceOrder;
 
 #[test]
 fn test_find_files_in_branch_one_file() {
@@ -19,15 +22,19 @@ fn test_find_files_in_branch_one_file() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files =
         process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap();
 
-    assert_eq!(master_files.len(), 1);
-    assert_eq!(master_files[0].0, "db/migrate/new/01__file_of_my_life.txt");
+    assert_eq!(master_files.renames.len(), 1);
     assert_eq!(
-        master_files[0].1,
+        master_files.renames[0].source,
+        "db/migrate/new/01__file_of_my_life.txt"
+    );
+    assert_eq!(
+        master_files.renames[0].target,
         "db/migrate/1/V20240102.01__file_of_my_life.txt"
     );
 
@@ -49,15 +56,19 @@ fn test_find_files_in_branch_one_file_same_day() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files =
         process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
 
-    assert_eq!(master_files.len(), 1);
-    assert_eq!(master_files[0].0, "db/migrate/new/01__file_of_my_life.txt");
+    assert_eq!(master_files.renames.len(), 1);
+    assert_eq!(
+        master_files.renames[0].source,
+        "db/migrate/new/01__file_of_my_life.txt"
+    );
     assert_eq!(
-        master_files[0].1,
+        master_files.renames[0].target,
         "db/migrate/1/V20240101.02__file_of_my_life.txt"
     );
 
@@ -85,16 +96,29 @@ fn test_find_files_in_branch_two_files_index_off_one() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files =
         process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
 
-    assert_eq!(master_files.len(), 2);
-    assert_eq!(master_files[0].0, "db/migrate/new/01__file3.txt");
-    assert_eq!(master_files[0].1, "db/migrate/1/V20240101.03__file3.txt");
-    assert_eq!(master_files[1].0, "db/migrate/new/02__file4.txt");
-    assert_eq!(master_files[1].1, "db/migrate/1/V20240101.04__file4.txt");
+    assert_eq!(master_files.renames.len(), 2);
+    assert_eq!(
+        master_files.renames[0].source,
+        "db/migrate/new/01__file3.txt"
+    );
+    assert_eq!(
+        master_files.renames[0].target,
+        "db/migrate/1/V20240101.03__file3.txt"
+    );
+    assert_eq!(
+        master_files.renames[1].source,
+        "db/migrate/new/02__file4.txt"
+    );
+    assert_eq!(
+        master_files.renames[1].target,
+        "db/migrate/1/V20240101.04__file4.txt"
+    );
 
     temp_dir.close().unwrap();
 }
@@ -117,15 +141,19 @@ fn test_find_files_in_branch_bad_code() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files =
         process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
 
-    assert_eq!(master_files.len(), 1);
-    assert_eq!(master_files[0].0, "db/migrate/new/01__file_of_my_life.txt");
+    assert_eq!(master_files.renames.len(), 1);
     assert_eq!(
-        master_files[0].1,
+        master_files.renames[0].source,
+        "db/migrate/new/01__file_of_my_life.txt"
+    );
+    assert_eq!(
+        master_files.renames[0].target,
         "db/migrate/1/V20240101.03__file_of_my_life.txt"
     );
 
@@ -147,6 +175,7 @@ fn test_find_files_in_branch_last_file_bad_code() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
@@ -156,8 +185,9 @@ fn test_find_files_in_branch_last_file_bad_code() {
     let err = master_files.unwrap_err();
     assert_eq!(
         err.to_string(),
-        "Can't extract the date and index from: \"db/migrate/1/V20240101__file1.txt\""
+        "Can't find a script following the naming scheme in the target, rejected: [\"db/migrate/1/V20240101__file1.txt\"]"
     );
+    assert_eq!(find_error(&err).unwrap().exit_code(), 12);
 
     temp_dir.close().unwrap();
 }
@@ -177,6 +207,7 @@ fn test_find_files_in_branch_non_existing_root() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
@@ -188,6 +219,7 @@ fn test_find_files_in_branch_non_existing_root() {
         err.to_string(),
         "Can't find the target folder prefix '\"bad_root\"' in: \"db/migrate/1/V20240101__file1.txt\""
     );
+    assert_eq!(find_error(&err).unwrap().exit_code(), 14);
 
     temp_dir.close().unwrap();
 }
@@ -207,6 +239,7 @@ fn test_find_files_in_branch_non_existing_target_branch() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
@@ -215,6 +248,7 @@ fn test_find_files_in_branch_non_existing_target_branch() {
 
     let err = master_files.unwrap_err();
     assert_eq!(err.to_string(), "Can't open target branch: bad_branch");
+    assert_eq!(find_error(&err).unwrap().exit_code(), 11);
 
     temp_dir.close().unwrap();
 }
@@ -234,6 +268,7 @@ fn test_find_files_in_branch_non_existing_source_branch() {
         extension_filter: Some("txt".to_string()),
         target_directory_filter: None,
         source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
     };
 
     let master_files = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
@@ -245,3 +280,734 @@ fn test_find_files_in_branch_non_existing_source_branch() {
 
     temp_dir.close().unwrap();
 }
+
+#[test]
+fn test_find_files_in_branch_skips_merged_sources() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/1/V20240101.01__file1.txt".to_string(),
+            "db/migrate/new/01__already_merged.txt".to_string(),
+        ],
+        vec!["db/migrate/new/02__file_of_my_life.txt".to_string()],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: Some(PathBuf::from("db/migrate/1")),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    };
+
+    let master_files =
+        process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+    assert_eq!(master_files.renames.len(), 1);
+    assert_eq!(
+        master_files.renames[0].source,
+        "db/migrate/new/02__file_of_my_life.txt"
+    );
+    assert_eq!(
+        master_files.renames[0].target,
+        "db/migrate/1/V20240101.02__file_of_my_life.txt"
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_version_folder_order() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/9/V20240105.01__file1.txt".to_string(),
+            "db/migrate/10/V20240101.01__file2.txt".to_string(),
+            "db/migrate/10/V20240101.02__file3.txt".to_string(),
+            "db/migrate/10/readme.txt".to_string(),
+        ],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    };
+
+    let master_files =
+        process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+    assert_eq!(master_files.renames.len(), 1);
+    assert_eq!(
+        master_files.renames[0].target,
+        "db/migrate/10/V20240101.03__file_of_my_life.txt"
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_plan_serializes_to_json() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap();
+    let json = serde_json::to_value(&plan).unwrap();
+
+    let blob = repo.blob(b"content").unwrap().to_string();
+    let source_commit = repo.revparse_single("develop").unwrap().id().to_string();
+    let target_commit = repo.revparse_single("master").unwrap().id().to_string();
+
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "source_branch": "develop",
+            "target_branch": "master",
+            "source_commit": source_commit,
+            "target_commit": target_commit,
+            "anchor": {
+                "path": "db/migrate/1/V20240101.01__file1.txt",
+                "code": { "date": "2024-01-01", "index": 1 }
+            },
+            "renames": [{
+                "source": "db/migrate/new/01__file_of_my_life.txt",
+                "target": "db/migrate/1/V20240102.01__file_of_my_life.txt",
+                "blob": blob,
+                "kind": "versioned",
+                "code": { "date": "2024-01-02", "index": 1 }
+            }],
+            "merged": [],
+            "edited": []
+        })
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_revspecs() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec!["db/migrate/new/01__file_of_my_life.txt".to_string()],
+    );
+
+    let master = repo.revparse_single("master").unwrap().id();
+    repo.tag_lightweight("release", &repo.find_object(master, None).unwrap(), false)
+        .unwrap();
+
+    for (target, source) in [
+        (master.to_string(), "develop".to_string()),
+        ("release".to_string(), "HEAD".to_string()),
+        ("develop~1".to_string(), "refs/heads/develop".to_string()),
+    ] {
+        let config = Configuration {
+            repo_path: temp_dir.path().to_path_buf(),
+            root_directory: "db/migrate".to_string(),
+            target_branch: target,
+            source_branch: source,
+            extension_filter: Some("txt".to_string()),
+            target_directory_filter: None,
+            source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+            ..Default::default()
+        };
+
+        let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap();
+
+        assert_eq!(plan.target_commit, master.to_string());
+        assert_eq!(plan.renames.len(), 1);
+        assert_eq!(
+            plan.renames[0].target,
+            "db/migrate/1/V20240102.01__file_of_my_life.txt"
+        );
+    }
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_flyway_kinds() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/1/V20240101.01__file1.txt".to_string(),
+            "db/migrate/1/R__view.txt".to_string(),
+            "db/migrate/1/afterMigrate.txt".to_string(),
+        ],
+        vec![
+            "db/migrate/new/01__table.txt".to_string(),
+            "db/migrate/new/02__column.txt".to_string(),
+            "db/migrate/new/R__report.txt".to_string(),
+            "db/migrate/new/U01__table.txt".to_string(),
+            "db/migrate/new/beforeMigrate.txt".to_string(),
+        ],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+    let targets: Vec<(&str, &str)> = plan
+        .renames
+        .iter()
+        .map(|rename| (rename.source.as_str(), rename.target.as_str()))
+        .collect();
+
+    assert_eq!(
+        targets,
+        vec![
+            (
+                "db/migrate/new/01__table.txt",
+                "db/migrate/1/V20240101.02__table.txt"
+            ),
+            (
+                "db/migrate/new/02__column.txt",
+                "db/migrate/1/V20240101.03__column.txt"
+            ),
+            ("db/migrate/new/R__report.txt", "db/migrate/1/R__report.txt"),
+            (
+                "db/migrate/new/beforeMigrate.txt",
+                "db/migrate/1/beforeMigrate.txt"
+            ),
+            (
+                "db/migrate/new/U01__table.txt",
+                "db/migrate/1/U20240101.02__table.txt"
+            ),
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_baseline_and_released_undo() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/1/V20240101.01__file1.txt".to_string(),
+            "db/migrate/2/V20240105.01__file2.txt".to_string(),
+        ],
+        vec![
+            "db/migrate/new/B__baseline.txt".to_string(),
+            "db/migrate/new/U__file1.txt".to_string(),
+        ],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 5).unwrap()).unwrap();
+
+    let targets: Vec<(&str, ScriptKind)> = plan
+        .renames
+        .iter()
+        .map(|rename| (rename.target.as_str(), rename.kind))
+        .collect();
+
+    // The undo script reverts a released script and lands next to it
+    assert_eq!(
+        targets,
+        vec![
+            (
+                "db/migrate/2/B20240105.02__baseline.txt",
+                ScriptKind::Baseline
+            ),
+            ("db/migrate/1/U20240101.01__file1.txt", ScriptKind::Undo),
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_companions_share_code() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![
+            "db/migrate/new/01__create.up.txt".to_string(),
+            "db/migrate/new/01__create.down.txt".to_string(),
+            "db/migrate/new/02__seed.txt".to_string(),
+            "db/migrate/new/02__seed.rollback.txt".to_string(),
+        ],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        companions: vec![
+            ".up/.down".parse().unwrap(),
+            ".txt/.rollback.txt".parse().unwrap(),
+        ],
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+    let targets: Vec<&str> = plan
+        .renames
+        .iter()
+        .map(|rename| rename.target.as_str())
+        .collect();
+
+    assert_eq!(
+        targets,
+        vec![
+            "db/migrate/1/V20240101.02__create.down.txt",
+            "db/migrate/1/V20240101.02__create.up.txt",
+            "db/migrate/1/V20240101.03__seed.rollback.txt",
+            "db/migrate/1/V20240101.03__seed.txt",
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_directory_layout() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/V20240101.01__init/up.txt".to_string(),
+            "db/migrate/V20240101.01__init/down.txt".to_string(),
+        ],
+        vec![
+            "db/migrate/new/01__create_users/up.txt".to_string(),
+            "db/migrate/new/01__create_users/down.txt".to_string(),
+            "db/migrate/new/02__add_email/up.txt".to_string(),
+            "db/migrate/new/notes.txt".to_string(),
+        ],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        layout: Layout::Directories,
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+    assert_eq!(plan.anchor.unwrap().path, "db/migrate/V20240101.01__init");
+
+    let targets: Vec<(&str, &str)> = plan
+        .renames
+        .iter()
+        .map(|rename| (rename.source.as_str(), rename.target.as_str()))
+        .collect();
+
+    assert_eq!(
+        targets,
+        vec![
+            (
+                "db/migrate/new/01__create_users",
+                "db/migrate/V20240101.02__create_users"
+            ),
+            (
+                "db/migrate/new/02__add_email",
+                "db/migrate/V20240101.03__add_email"
+            ),
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_timestamp_scheme() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/20240101235959_init.txt".to_string()],
+        vec![
+            "db/migrate/new/01_create_users.txt".to_string(),
+            "db/migrate/new/02_add_email.txt".to_string(),
+        ],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        naming_scheme: Box::new(TimestampScheme::default()),
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+    let targets: Vec<&str> = plan
+        .renames
+        .iter()
+        .map(|rename| rename.target.as_str())
+        .collect();
+
+    assert_eq!(
+        targets,
+        vec![
+            "db/migrate/20240102000000_create_users.txt",
+            "db/migrate/20240102000001_add_email.txt",
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_diesel_directories() {
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "migrations/00000000000000_diesel_initial_setup/up.sql".to_string(),
+            "migrations/00000000000000_diesel_initial_setup/down.sql".to_string(),
+            "migrations/2024-01-01-101500_create_users/up.sql".to_string(),
+            "migrations/2024-01-01-101500_create_users/down.sql".to_string(),
+        ],
+        vec![
+            "migrations/new/2024-03-05-083000_add_email/up.sql".to_string(),
+            "migrations/new/2024-03-05-083000_add_email/down.sql".to_string(),
+            "migrations/new/2024-03-06-120000_add_index/up.sql".to_string(),
+        ],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "migrations".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("sql".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("migrations/new")),
+        layout: Layout::Directories,
+        naming_scheme: Box::new(TimestampScheme::diesel("_")),
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+    assert_eq!(
+        plan.anchor.unwrap().path,
+        "migrations/2024-01-01-101500_create_users"
+    );
+
+    let targets: Vec<(&str, &str)> = plan
+        .renames
+        .iter()
+        .map(|rename| (rename.source.as_str(), rename.target.as_str()))
+        .collect();
+
+    assert_eq!(
+        targets,
+        vec![
+            (
+                "migrations/new/2024-03-05-083000_add_email",
+                "migrations/2024-01-01-101501_add_email"
+            ),
+            (
+                "migrations/new/2024-03-06-120000_add_index",
+                "migrations/2024-01-01-101502_add_index"
+            ),
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_find_files_in_branch_pattern_with_own_names() {
+    // Legacy names next to the ones the tool wrote since
+    let (temp_dir, _) = create_test_repository(
+        vec![
+            "db/migrate/v2024_01_01_03-init.txt".to_string(),
+            "db/migrate/V20240102.01__add_users.txt".to_string(),
+        ],
+        vec!["db/migrate/new/01__add_email.txt".to_string()],
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        target_directory_filter: None,
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        naming_scheme: Box::new(
+            PatternScheme::new(
+                r"^v(?<date>\d{4}_\d\d_\d\d)_(?<index>\d+)-(?<name>.+)$",
+                Scheme::DateIndex,
+                Box::new(DateIndexScheme::default()),
+            )
+            .unwrap(),
+        ),
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap();
+
+    assert_eq!(
+        plan.anchor.unwrap().path,
+        "db/migrate/V20240102.01__add_users.txt"
+    );
+    assert_eq!(plan.renames.len(), 1);
+    assert_eq!(
+        plan.renames[0].target,
+        "db/migrate/V20240102.02__add_email.txt"
+    );
+
+    temp_dir.close().unwrap();
+}
+
+fn ordering_configuration(repo_path: PathBuf, source_order: SourceOrder) -> Configuration {
+    Configuration {
+        repo_path,
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        source_order,
+        ..Default::default()
+    }
+}
+
+fn sources(config: Configuration) -> Vec<String> {
+    process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+        .unwrap()
+        .renames
+        .into_iter()
+        .map(|rename| rename.source)
+        .collect()
+}
+
+#[test]
+fn test_source_order_by_numeric_prefix() {
+    let (temp_dir, _) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![
+            "db/migrate/new/10__x.txt".to_string(),
+            "db/migrate/new/2__y.txt".to_string(),
+            "db/migrate/new/1__z.txt".to_string(),
+        ],
+    );
+
+    assert_eq!(
+        sources(ordering_configuration(
+            temp_dir.path().to_path_buf(),
+            SourceOrder::Name
+        )),
+        vec![
+            "db/migrate/new/1__z.txt",
+            "db/migrate/new/2__y.txt",
+            "db/migrate/new/10__x.txt",
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_source_order_by_first_commit() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![],
+    );
+    commit_files(&repo, &["db/migrate/new/b.txt"], "b", 1_700_000_000);
+    commit_files(&repo, &["db/migrate/new/c.txt"], "c", 1_700_000_100);
+    commit_files(&repo, &["db/migrate/new/a.txt"], "a", 1_700_000_200);
+    // A later change doesn't move the script
+    commit_files(&repo, &["db/migrate/new/b.txt"], "b2", 1_700_000_300);
+
+    assert_eq!(
+        sources(ordering_configuration(
+            temp_dir.path().to_path_buf(),
+            SourceOrder::Commit
+        )),
+        vec![
+            "db/migrate/new/b.txt",
+            "db/migrate/new/c.txt",
+            "db/migrate/new/a.txt",
+        ]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_source_order_by_manifest() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![
+            "db/migrate/new/a.txt".to_string(),
+            "db/migrate/new/b.txt".to_string(),
+        ],
+    );
+
+    let config = ordering_configuration(temp_dir.path().to_path_buf(), SourceOrder::Manifest);
+    let err = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Can't find the manifest \"db/migrate/new/.fmr-order\" in the source branch"
+    );
+
+    commit_files(
+        &repo,
+        &["db/migrate/new/.fmr-order"],
+        "b.txt\na.txt\n",
+        1_700_000_000,
+    );
+
+    assert_eq!(
+        sources(ordering_configuration(
+            temp_dir.path().to_path_buf(),
+            SourceOrder::Manifest
+        )),
+        vec!["db/migrate/new/b.txt", "db/migrate/new/a.txt"]
+    );
+
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn test_already_merged_sources_are_skipped() {
+    let (temp_dir, repo) = create_test_repository(
+        vec!["db/migrate/1/V20240101.01__file1.txt".to_string()],
+        vec![],
+    );
+    // First merge: the scripts landed in the target under their new names
+    commit_files(
+        &repo,
+        &[
+            "db/migrate/new/01__same_blob.txt",
+            "db/migrate/new/02__edited.txt",
+        ],
+        "first",
+        1_700_000_000,
+    );
+    repo.set_head("refs/heads/master").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+    commit_files(
+        &repo,
+        &["db/migrate/1/V20240102.01__same_blob.txt"],
+        "first",
+        1_700_000_100,
+    );
+    commit_files(
+        &repo,
+        &["db/migrate/1/V20240102.02__edited.txt"],
+        "old",
+        1_700_000_100,
+    );
+    repo.set_head("refs/heads/develop").unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+    // The branch goes on with an edit and a new script
+    commit_files(
+        &repo,
+        &["db/migrate/new/02__edited.txt"],
+        "new",
+        1_700_000_200,
+    );
+    commit_files(
+        &repo,
+        &["db/migrate/new/03__new.txt"],
+        "third",
+        1_700_000_300,
+    );
+
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap();
+
+    assert_eq!(
+        plan.merged,
+        vec![
+            Merged {
+                source: "db/migrate/new/01__same_blob.txt".to_string(),
+                target: "db/migrate/1/V20240102.01__same_blob.txt".to_string(),
+            },
+            Merged {
+                source: "db/migrate/new/02__edited.txt".to_string(),
+                target: "db/migrate/1/V20240102.02__edited.txt".to_string(),
+            },
+        ]
+    );
+    assert_eq!(plan.renames.len(), 1);
+    assert_eq!(plan.renames[0].target, "db/migrate/1/V20240102.03__new.txt");
+
+    // The manifest only has to list the scripts which still need a code
+    commit_files(
+        &repo,
+        &["db/migrate/new/.fmr-order"],
+        "03__new.txt\n",
+        1_700_000_400,
+    );
+    let config = Configuration {
+        repo_path: temp_dir.path().to_path_buf(),
+        root_directory: "db/migrate".to_string(),
+        target_branch: "master".to_string(),
+        source_branch: "develop".to_string(),
+        extension_filter: Some("txt".to_string()),
+        source_directory_filter: Some(PathBuf::from("db/migrate/new")),
+        source_order: SourceOrder::Manifest,
+        ..Default::default()
+    };
+
+    let plan = process(config, chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()).unwrap();
+
+    assert_eq!(plan.merged.len(), 2);
+    assert_eq!(plan.renames.len(), 1);
+    assert_eq!(plan.renames[0].target, "db/migrate/1/V20240102.03__new.txt");
+
+    temp_dir.close().unwrap();
+}
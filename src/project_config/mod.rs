@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::cli::Settings;
+use crate::error::Error;
+
+#[cfg(test)]
+mod tests;
+
+pub const FILE_NAME: &str = ".fmr.toml";
+
+/// Content of the `.fmr.toml` project file.
+///
+/// The top level holds the defaults, each entry of `[profiles]` overrides them.
+/// Unknown keys are rejected on both levels, so that a typo doesn't drop a setting.
+#[derive(Debug, Default, Deserialize)]
+#[serde(try_from = "toml::Table")]
+pub struct ProjectConfig {
+    pub defaults: Settings,
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+// `deny_unknown_fields` doesn't work with a flattened `Settings`, so the profiles are
+// split off by hand before the rest is read as the defaults
+impl TryFrom<toml::Table> for ProjectConfig {
+    type Error = toml::de::Error;
+
+    fn try_from(mut table: toml::Table) -> Result<Self, Self::Error> {
+        let profiles = match table.remove("profiles") {
+            Some(profiles) => profiles.try_into()?,
+            None => BTreeMap::new(),
+        };
+
+        Ok(ProjectConfig {
+            defaults: toml::Value::Table(table).try_into()?,
+            profiles,
+        })
+    }
+}
+
+impl ProjectConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Can't read the project file: {:?}", path))?;
+
+        toml::from_str(&content).with_context(|| format!("Invalid project file: {:?}", path))
+    }
+
+    /// Returns the settings of `profile` on top of the defaults.
+    pub fn settings(mut self, profile: Option<&str>) -> anyhow::Result<Settings> {
+        let Some(name) = profile else {
+            return Ok(self.defaults);
+        };
+
+        let profile = self.profiles.remove(name).with_context(|| {
+            format!(
+                "Unknown profile '{}', available: {:?}",
+                name,
+                self.profiles.keys().collect::<Vec<_>>()
+            )
+        })?;
+
+        Ok(profile.or(self.defaults))
+    }
+}
+
+/// Looks for the project file in the root of the repository containing `repo_path`.
+pub fn find_project_config(repo_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let repo = gix::discover(repo_path).map_err(|err| Error::RepositoryNotFound {
+        path: repo_path.to_path_buf(),
+        source: err.into(),
+    })?;
+
+    Ok(repo
+        .work_dir()
+        .map(|dir| dir.join(FILE_NAME))
+        .filter(|path| path.is_file()))
+}
+
+/// Completes the command line `settings` from the project file, command line values win.
+pub fn resolve_settings(
+    settings: Settings,
+    config: Option<&Path>,
+    profile: Option<&str>,
+) -> anyhow::Result<Settings> {
+    let path = match config {
+        Some(path) => Some(path.to_path_buf()),
+        None => find_project_config(Path::new(settings.repo_path.as_deref().unwrap_or(".")))?,
+    };
+
+    let project = match path {
+        Some(path) => ProjectConfig::load(&path)?,
+        None if profile.is_some() => anyhow::bail!("No {} found for the profile", FILE_NAME),
+        None => return Ok(settings),
+    };
+
+    Ok(settings.or(project.settings(profile)?))
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use crate::cli::Settings;
+use crate::git::tests::internal::create_test_repository;
+
+use super::{resolve_settings, ProjectConfig, FILE_NAME};
+
+const PROJECT_FILE: &str = r#"
+root-dir = "db/migrate"
+target = "develop"
+ext = "sql"
+
+[profiles.migrations]
+source-filter = "db/migrate/new"
+
+[profiles.seeds]
+root-dir = "db/seed"
+ext = "csv"
+source-filter = "db/seed/new"
+"#;
+
+#[test]
+fn profile_overrides_defaults() -> anyhow::Result<()> {
+    let project: ProjectConfig = toml::from_str(PROJECT_FILE)?;
+
+    let settings = project.settings(Some("seeds"))?;
+
+    assert_eq!(settings.root_dir.as_deref(), Some("db/seed"));
+    assert_eq!(settings.target_branch.as_deref(), Some("develop"));
+    assert_eq!(settings.extension.as_deref(), Some("csv"));
+    assert_eq!(
+        settings.source_directory_filter.as_deref(),
+        Some("db/seed/new")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unknown_profile() -> anyhow::Result<()> {
+    let project: ProjectConfig = toml::from_str(PROJECT_FILE)?;
+
+    let result = project.settings(Some("other"));
+
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Unknown profile 'other', available: [\"migrations\", \"seeds\"]"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn command_line_wins_over_project_file() -> anyhow::Result<()> {
+    let (dir, _) = create_test_repository(vec!["readme.txt".to_string()], vec![]);
+    std::fs::write(dir.path().join(FILE_NAME), PROJECT_FILE)?;
+
+    let settings = Settings {
+        repo_path: Some(dir.path().to_string_lossy().to_string()),
+        extension: Some("txt".to_string()),
+        source_branch: Some("feature".to_string()),
+        ..Default::default()
+    };
+
+    let settings = resolve_settings(settings, None, Some("migrations"))?;
+
+    assert_eq!(settings.root_dir.as_deref(), Some("db/migrate"));
+    assert_eq!(settings.target_branch.as_deref(), Some("develop"));
+    assert_eq!(settings.source_branch.as_deref(), Some("feature"));
+    assert_eq!(settings.extension.as_deref(), Some("txt"));
+    assert_eq!(
+        settings.source_directory_filter.as_deref(),
+        Some("db/migrate/new")
+    );
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn no_project_file() -> anyhow::Result<()> {
+    let (dir, _) = create_test_repository(vec!["readme.txt".to_string()], vec![]);
+
+    let settings = Settings {
+        repo_path: Some(dir.path().to_string_lossy().to_string()),
+        ..Default::default()
+    };
+
+    let settings = resolve_settings(settings, None, None)?;
+    assert_eq!(settings.root_dir, None);
+
+    let result = resolve_settings(Settings::default(), Some(Path::new("missing.toml")), None);
+    assert!(result.is_err());
+
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn misspelled_keys_are_rejected() {
+    let result = toml::from_str::<ProjectConfig>("target_branch = \"main\"\n");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("unknown field `target_branch`"));
+
+    let result = toml::from_str::<ProjectConfig>("[profiles.seeds]\nsepparator = \"_\"\n");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("unknown field `sepparator`"));
+}
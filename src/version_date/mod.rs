@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use chrono::{FixedOffset, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::git::commit_time;
+use crate::process::Configuration;
+
+#[cfg(test)]
+mod tests;
+
+/// Where the date of the new codes comes from.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DateSource {
+    /// The current day
+    #[default]
+    Today,
+    /// The committer time of the source branch tip
+    Commit,
+    /// An explicit day
+    Fixed(NaiveDate),
+}
+
+impl FromStr for DateSource {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "today" => Ok(DateSource::Today),
+            "commit" => Ok(DateSource::Commit),
+            _ => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(DateSource::Fixed)
+                .with_context(|| {
+                    format!("Expected 'today', 'commit' or YYYY-MM-DD, got: {}", value)
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for DateSource {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> anyhow::Result<Self> {
+        value.parse()
+    }
+}
+
+/// Timezone the date is taken in.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Timezone {
+    Local,
+    Utc,
+    Offset(FixedOffset),
+}
+
+impl FromStr for Timezone {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "local" => Ok(Timezone::Local),
+            "utc" => Ok(Timezone::Utc),
+            _ => value
+                .parse::<FixedOffset>()
+                .map(Timezone::Offset)
+                .with_context(|| {
+                    format!(
+                        "Expected 'local', 'utc' or an offset like +02:00, got: {}",
+                        value
+                    )
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for Timezone {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> anyhow::Result<Self> {
+        value.parse()
+    }
+}
+
+/// Resolves the day the new codes are generated for.
+///
+/// Without a timezone the current day is taken in the local timezone and the commit
+/// day in the timezone the commit was recorded with, so it's the same on every machine.
+pub fn resolve_date(
+    source: &DateSource,
+    timezone: Option<&Timezone>,
+    config: &Configuration,
+) -> anyhow::Result<NaiveDate> {
+    let now = || Utc::now().fixed_offset();
+
+    let time = match source {
+        DateSource::Fixed(date) => return Ok(*date),
+        DateSource::Today => now(),
+        DateSource::Commit => commit_time(config.repo_path.as_path(), &config.source_branch)
+            .with_context(|| format!("Can't open source branch: {0}", &config.source_branch))?,
+    };
+
+    let date = match timezone {
+        Some(Timezone::Local) => time.with_timezone(&chrono::Local).date_naive(),
+        Some(Timezone::Utc) => time.with_timezone(&Utc).date_naive(),
+        Some(Timezone::Offset(offset)) => time.with_timezone(offset).date_naive(),
+        None if *source == DateSource::Today => time.with_timezone(&chrono::Local).date_naive(),
+        None => time.date_naive(),
+    };
+
+    Ok(date)
+}
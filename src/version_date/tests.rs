@@ -0,0 +1,79 @@
+use chrono::NaiveDate;
+
+use crate::git::tests::internal::create_test_repository;
+use crate::process::Configuration;
+
+use super::{resolve_date, DateSource, Timezone};
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn parse_date_source() {
+    assert_eq!("today".parse::<DateSource>().unwrap(), DateSource::Today);
+    assert_eq!("commit".parse::<DateSource>().unwrap(), DateSource::Commit);
+    assert_eq!(
+        "2024-01-02".parse::<DateSource>().unwrap(),
+        DateSource::Fixed(date(2024, 1, 2))
+    );
+    assert!("20240102".parse::<DateSource>().is_err());
+}
+
+#[test]
+fn parse_timezone() {
+    assert_eq!("UTC".parse::<Timezone>().unwrap(), Timezone::Utc);
+    assert_eq!("local".parse::<Timezone>().unwrap(), Timezone::Local);
+    assert_eq!(
+        "+02:00".parse::<Timezone>().unwrap(),
+        Timezone::Offset(chrono::FixedOffset::east_opt(7200).unwrap())
+    );
+    assert!("Europe/Prague".parse::<Timezone>().is_err());
+}
+
+#[test]
+fn fixed_date() {
+    let config = Configuration::default();
+
+    let result = resolve_date(
+        &DateSource::Fixed(date(2024, 1, 2)),
+        Some(&Timezone::Utc),
+        &config,
+    )
+    .unwrap();
+
+    assert_eq!(result, date(2024, 1, 2));
+}
+
+#[test]
+fn commit_date() {
+    let (dir, repo) = create_test_repository(vec!["test.sql".to_string()], vec![]);
+
+    // 2024-01-01 23:30 UTC, committed from +02:00
+    let time = git2::Time::new(1704151800, 120);
+    let sig = git2::Signature::new("script_rename", "script_rename@example.com", &time).unwrap();
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    repo.commit(
+        Some("HEAD"),
+        &sig,
+        &sig,
+        "Late commit",
+        &head.tree().unwrap(),
+        &[&head],
+    )
+    .unwrap();
+
+    let config = Configuration {
+        repo_path: dir.path().to_path_buf(),
+        source_branch: "develop".to_string(),
+        ..Default::default()
+    };
+
+    let commit_zone = resolve_date(&DateSource::Commit, None, &config).unwrap();
+    let utc = resolve_date(&DateSource::Commit, Some(&Timezone::Utc), &config).unwrap();
+
+    assert_eq!(commit_zone, date(2024, 1, 2));
+    assert_eq!(utc, date(2024, 1, 1));
+
+    dir.close().unwrap();
+}